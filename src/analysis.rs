@@ -0,0 +1,356 @@
+//! Post-game analysis: how much each move of a game gave away.
+//!
+//! Every position of the game is searched with minimax. A move's score is
+//! its value in the search of the position before it, or, for moves the
+//! search did not consider, minus the value of the position after it
+//! searched one ply shallower. The swing of a move is how far its score
+//! falls short of the best move's, and labels the move:
+//!
+//! | Label        | Swing                                   |
+//! |--------------|-----------------------------------------|
+//! | `Best`       | the best move, or as good               |
+//! | `Good`       | up to 100, about an open two            |
+//! | `Inaccuracy` | up to 300, about an open three          |
+//! | `Mistake`    | up to 1000, about a four                |
+//! | `Blunder`    | more than 1000                          |
+//! | `MissedWin`  | a forced win was available and not kept |
+//!
+//! The analysis can be printed as a report or saved as an SGF game record
+//! with the labels as move annotations and the scores as comments.
+
+use std::fmt;
+
+use crate::{
+    BOARD_SIZE, Cell, Gomoku, MinimaxOptions, SearchControl, WIN_SCORE, default_threads,
+};
+
+/// Largest swing of a good move.
+const GOOD_SWING: i32 = 100;
+/// Largest swing of an inaccuracy.
+const INACCURACY_SWING: i32 = 300;
+/// Largest swing of a mistake; anything more is a blunder.
+const MISTAKE_SWING: i32 = 1000;
+
+/// How good a move was compared with the best one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MoveLabel {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+    /// The player could force a win and played a move that does not.
+    MissedWin,
+}
+
+impl MoveLabel {
+    /// Every label, best first.
+    pub const ALL: [MoveLabel; 6] = [
+        MoveLabel::Best,
+        MoveLabel::Good,
+        MoveLabel::Inaccuracy,
+        MoveLabel::Mistake,
+        MoveLabel::Blunder,
+        MoveLabel::MissedWin,
+    ];
+
+    /// Name of the label, e.g. `missed_win`.
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveLabel::Best => "best",
+            MoveLabel::Good => "good",
+            MoveLabel::Inaccuracy => "inaccuracy",
+            MoveLabel::Mistake => "mistake",
+            MoveLabel::Blunder => "blunder",
+            MoveLabel::MissedWin => "missed_win",
+        }
+    }
+
+    /// SGF move annotation: tesuji for the best move, doubtful for an
+    /// inaccuracy and a bad move otherwise, emphasized for the worst.
+    fn sgf_annotation(self) -> &'static str {
+        match self {
+            MoveLabel::Best => "TE[1]",
+            MoveLabel::Good => "",
+            MoveLabel::Inaccuracy => "DO[]",
+            MoveLabel::Mistake => "BM[1]",
+            MoveLabel::Blunder | MoveLabel::MissedWin => "BM[2]",
+        }
+    }
+}
+
+/// One analyzed move. Scores are from the point of view of the player who
+/// made the move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveAnalysis {
+    pub player: Cell,
+    pub played: (usize, usize),
+    /// Score of the played move.
+    pub score: i32,
+    /// The best move found by the search and its score.
+    pub best_move: (usize, usize),
+    pub best_score: i32,
+    /// How much worse the played move scored than the best, never negative.
+    pub swing: i32,
+    pub label: MoveLabel,
+}
+
+/// The analysis of every move of a game, in the order they were played.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameAnalysis {
+    /// Stones on the board before the first move, as placed when setting
+    /// up a position, in row-major order.
+    pub setup: Vec<((usize, usize), Cell)>,
+    pub moves: Vec<MoveAnalysis>,
+}
+
+impl GameAnalysis {
+    /// Number of moves of `player` with `label`.
+    pub fn count(&self, player: Cell, label: MoveLabel) -> usize {
+        self.moves
+            .iter()
+            .filter(|m| m.player == player && m.label == label)
+            .count()
+    }
+
+    /// The game as an SGF record for Gomoku (`GM[4]`). Set-up stones are
+    /// added to the root node with `AB` and `AW`. Each move carries its
+    /// label as a move annotation, its score as the node value `V` and a
+    /// comment naming the label and the best move.
+    pub fn to_sgf(&self) -> String {
+        let mut sgf = format!("(;FF[4]GM[4]SZ[{}]AP[gomoku]", BOARD_SIZE);
+        for (property, player) in [("AB", Cell::Black), ("AW", Cell::White)] {
+            let points: String = self
+                .setup
+                .iter()
+                .filter(|&&(_, cell)| cell == player)
+                .map(|&((row, col), _)| format!("[{}{}]", sgf_coord(col), sgf_coord(row)))
+                .collect();
+            if !points.is_empty() {
+                sgf.push_str(property);
+                sgf.push_str(&points);
+            }
+        }
+        for m in &self.moves {
+            let color = if m.player == Cell::White { 'W' } else { 'B' };
+            let (row, col) = m.played;
+            sgf.push_str(&format!(";{}[{}{}]", color, sgf_coord(col), sgf_coord(row)));
+            sgf.push_str(m.label.sgf_annotation());
+            sgf.push_str(&format!("V[{}]C[{}]", m.score, comment(m)));
+        }
+        sgf.push_str(")\n");
+        sgf
+    }
+}
+
+/// SGF coordinate letter, `a` for the first row or column.
+fn sgf_coord(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+/// A move's label, score and the better move if there was one.
+fn comment(m: &MoveAnalysis) -> String {
+    let mut text = format!("{}, score {}", m.label.name().replace('_', " "), m.score);
+    if m.label != MoveLabel::Best {
+        text.push_str(&format!(
+            ", best ({}, {}) scoring {}",
+            m.best_move.0, m.best_move.1, m.best_score
+        ));
+    }
+    text
+}
+
+/// A report with one line per move and a count of each player's errors.
+impl fmt::Display for GameAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (ply, m) in self.moves.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}. {:<5} ({}, {}): {}",
+                ply + 1,
+                format!("{:?}", m.player),
+                m.played.0,
+                m.played.1,
+                comment(m)
+            )?;
+        }
+        for player in [Cell::Black, Cell::White] {
+            let counts: Vec<_> = MoveLabel::ALL[2..]
+                .iter()
+                .map(|&label| format!("{} {}", label.name().replace('_', " "), self.count(player, label)))
+                .collect();
+            writeln!(f, "{:?}: {}", player, counts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Label of a move that scored `score` where the best scored `best_score`.
+fn label(played_best: bool, score: i32, best_score: i32) -> MoveLabel {
+    let swing = best_score - score;
+    if played_best || swing <= 0 {
+        MoveLabel::Best
+    } else if best_score >= WIN_SCORE / 2 && score < WIN_SCORE / 2 {
+        MoveLabel::MissedWin
+    } else if swing <= GOOD_SWING {
+        MoveLabel::Good
+    } else if swing <= INACCURACY_SWING {
+        MoveLabel::Inaccuracy
+    } else if swing <= MISTAKE_SWING {
+        MoveLabel::Mistake
+    } else {
+        MoveLabel::Blunder
+    }
+}
+
+impl Gomoku {
+    /// Analyze the moves of the game with `depth`-ply minimax searches,
+    /// replayed from the position before the first move. Stones placed
+    /// without a move, as by setting up a position, stay on the board.
+    pub fn analyze_game(&self, depth: u32) -> GameAnalysis {
+        let depth = depth.max(1) as i32;
+        let options = MinimaxOptions::new(depth, default_threads());
+        let reply_options = MinimaxOptions::new(depth - 1, default_threads());
+        let mut position = self.clone();
+        for &(row, col) in &self.history {
+            position.set_cell(row, col, Cell::Empty);
+        }
+        position.history.clear();
+
+        let mut analysis = GameAnalysis::default();
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                if position.board[row][col] != Cell::Empty {
+                    analysis.setup.push(((row, col), position.board[row][col]));
+                }
+            }
+        }
+        for &(row, col) in &self.history {
+            let player = self.board[row][col];
+            position.current_player = player;
+            let search = position.minimax_search(&options, &mut SearchControl::default());
+
+            let known = search
+                .evaluations
+                .iter()
+                .find(|e| (e.row, e.col) == (row, col))
+                .map(|e| e.value);
+            position.set_cell(row, col, player);
+            position.history.push((row, col));
+            let score = if (row, col) == search.best_move {
+                search.score
+            } else if position.check_winner() == Some(player) {
+                WIN_SCORE
+            } else if let Some(value) = known {
+                value
+            } else if depth > 1 {
+                position.current_player = player.opponent();
+                -position
+                    .minimax_search(&reply_options, &mut SearchControl::default())
+                    .score
+            } else {
+                position.evaluate(player)
+            };
+
+            analysis.moves.push(MoveAnalysis {
+                player,
+                played: (row, col),
+                score,
+                best_move: search.best_move,
+                best_score: search.score,
+                swing: (search.score - score).max(0),
+                label: label((row, col) == search.best_move, score, search.score),
+            });
+        }
+        analysis
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Black builds an open three, White ignores it and Black fails to
+    /// make the winning four, until White finally blocks.
+    fn careless_game() -> Gomoku {
+        let mut game = Gomoku::new();
+        let moves = [
+            (7, 7),
+            (6, 8),
+            (7, 6),
+            (8, 8),
+            (7, 5),
+            (1, 1),
+            (12, 12),
+            (7, 4),
+        ];
+        for (row, col) in moves {
+            game.push_move(row, col).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    /// Ignoring an open three is a blunder and not finishing it a missed
+    /// win, and every swing and best move gets the matching label.
+    fn labels_follow_score_swings() {
+        let analysis = careless_game().analyze_game(2);
+        let labels: Vec<_> = analysis.moves.iter().map(|m| m.label).collect();
+        assert_eq!(labels.len(), 8);
+        assert_eq!(labels[5], MoveLabel::Blunder);
+        assert_eq!(labels[6], MoveLabel::MissedWin);
+        assert_eq!(analysis.moves[5].player, Cell::White);
+        assert_eq!(analysis.count(Cell::Black, MoveLabel::MissedWin), 1);
+        for m in &analysis.moves {
+            assert_eq!(m.swing, (m.best_score - m.score).max(0));
+            if m.played == m.best_move {
+                assert_eq!(m.label, MoveLabel::Best);
+            }
+        }
+        assert_eq!(label(false, 50, 100), MoveLabel::Good);
+        assert_eq!(label(false, -200, 100), MoveLabel::Inaccuracy);
+        assert_eq!(label(false, -800, 100), MoveLabel::Mistake);
+    }
+
+    #[test]
+    /// The SGF record lists every move with its label as an annotation.
+    fn sgf_annotates_moves() {
+        let sgf = careless_game().analyze_game(2).to_sgf();
+        assert!(sgf.starts_with("(;FF[4]GM[4]SZ[15]AP[gomoku];B[hh]"));
+        assert!(sgf.trim_end().ends_with(')'));
+        assert_eq!(sgf.matches(";B[").count() + sgf.matches(";W[").count(), 8);
+        assert!(sgf.contains(";W[bb]BM[2]V["));
+        assert!(sgf.contains(";B[mm]BM[2]V["));
+    }
+
+    #[test]
+    /// The report has a line per move and the error counts of each player.
+    fn report_lists_moves_and_totals() {
+        let report = careless_game().analyze_game(2).to_string();
+        assert_eq!(report.lines().count(), 10);
+        assert!(report.contains("  6. White (1, 1): blunder"));
+        let black = report.lines().find(|line| line.starts_with("Black:")).unwrap();
+        assert!(black.ends_with("blunder 0, missed win 1"), "{}", black);
+    }
+
+    #[test]
+    /// Stones set up before the first move stay on the board: with a
+    /// White four against the edge, Black's move elsewhere throws the
+    /// game away.
+    fn setup_stones_are_kept() {
+        let mut game = Gomoku::new();
+        for col in 0..4 {
+            game.set_cell(2, col, Cell::White);
+        }
+        game.push_move(12, 12).unwrap();
+        let analysis = game.analyze_game(2);
+        assert_eq!(analysis.setup.len(), 4);
+        assert_eq!(analysis.setup[0], ((2, 0), Cell::White));
+        assert_eq!(analysis.moves.len(), 1);
+        assert_eq!(analysis.moves[0].best_move, (2, 4));
+        assert_eq!(analysis.moves[0].label, MoveLabel::Blunder);
+
+        let sgf = analysis.to_sgf();
+        assert!(sgf.starts_with("(;FF[4]GM[4]SZ[15]AP[gomoku]AW[ac][bc][cc][dc];B[mm]"));
+    }
+}
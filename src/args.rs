@@ -0,0 +1,51 @@
+//! Command-line options shared by the console game and the tools.
+//!
+//! Options are written as `--name value` anywhere on the command line.
+
+use std::str::FromStr;
+
+/// Value following `name` on the command line, if the option is present.
+pub fn option_value(args: &[String], name: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|a| a == name) {
+        Some(i) => match args.get(i + 1) {
+            Some(value) => Ok(Some(value.clone())),
+            None => Err(format!("{} needs a value", name)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Value following `name` on the command line, parsed, or `default`.
+pub fn option_or<T: FromStr>(args: &[String], name: &str, default: T) -> Result<T, String> {
+    match option_value(args, name)? {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("Invalid value '{}' for {}", value, name)),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    /// Options are found anywhere, parsed, and reported when their value
+    /// is missing or malformed.
+    fn options_are_parsed() {
+        let args = args("tool --games 12 --out file.txt --seed");
+        assert_eq!(option_value(&args, "--out"), Ok(Some("file.txt".to_string())));
+        assert_eq!(option_value(&args, "--level"), Ok(None));
+        assert_eq!(option_or(&args, "--games", 20), Ok(12));
+        assert_eq!(option_or(&args, "--rounds", 50), Ok(50));
+        assert_eq!(option_or::<u64>(&args, "--seed", 0), Err("--seed needs a value".to_string()));
+        assert_eq!(
+            option_or::<u64>(&args, "--out", 0),
+            Err("Invalid value 'file.txt' for --out".to_string())
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Build an opening book from game records and self-play games.
+//!
+//! ```text
+//! cargo run --release --bin book -- book/openings.txt --self-play 100
+//! ```
+//!
+//! Record files hold one game per line as `row,col` moves separated by
+//! spaces, e.g. `7,7 6,8 8,6`; `#` starts a comment. Options:
+//! `--self-play <games>` (default 0) adds games the AI plays against
+//! itself, opening from the recorded lines, at `--level <name>`
+//! (`medium`) from `--seed <n>` (0), `--plies <n>` (8) limits how deep
+//! into each game moves are recorded, `--min-weight <n>` (1) drops rarer
+//! moves and `--out <file>` (`book/opening.book`) names the book. That
+//! file is embedded in the library and used by the console and browser
+//! games.
+
+use gomoku::{
+    option_or, BookEngine, Difficulty, DifficultyEngine, Engine, Gomoku, OpeningBook, SearchLimits,
+};
+use std::env;
+use std::fs;
+use std::process;
+
+/// Games in a record file, one per non-empty line.
+fn read_records(path: &str) -> Result<Vec<Vec<(usize, usize)>>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Cannot read '{}': {}", path, err))?;
+    let mut games = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let game = line
+            .split_whitespace()
+            .map(|mv| {
+                let (row, col) = mv.split_once(',')?;
+                Some((row.parse().ok()?, col.parse().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("{}:{}: expected moves like '7,7'", path, number + 1))?;
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// Play a game with both sides at `difficulty`, opening from `book`, and
+/// return its moves.
+fn self_play(book: &OpeningBook, difficulty: Difficulty, seed: u64) -> Vec<(usize, usize)> {
+    let mut engine = BookEngine::new(
+        book.clone(),
+        Box::new(DifficultyEngine::new(difficulty, seed)),
+        seed,
+    );
+    engine.set_threads(1);
+    let mut game = Gomoku::new();
+    while game.check_winner().is_none() && !game.is_board_full() {
+        let (row, col) = engine.choose_move(&game, &SearchLimits::default()).best_move;
+        game.push_move(row, col).expect("the engine plays legal moves");
+    }
+    game.moves().to_vec()
+}
+
+fn run() -> Result<(), String> {
+    let args: Vec<String> = env::args().collect();
+    let games: u64 = option_or(&args, "--self-play", 0)?;
+    let level: String = option_or(&args, "--level", "medium".to_string())?;
+    let seed: u64 = option_or(&args, "--seed", 0)?;
+    let plies: usize = option_or(&args, "--plies", 8)?;
+    let min_weight: u16 = option_or(&args, "--min-weight", 1)?;
+    let out: String = option_or(&args, "--out", "book/opening.book".to_string())?;
+    let difficulty =
+        Difficulty::from_name(&level).ok_or_else(|| format!("Unknown level '{}'", level))?;
+
+    // Positional arguments are record files; skip option values.
+    let mut files = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        if args[i].starts_with("--") {
+            i += 2;
+        } else {
+            files.push(args[i].clone());
+            i += 1;
+        }
+    }
+
+    let mut book = OpeningBook::new();
+    for file in &files {
+        let records = read_records(file)?;
+        let moves: usize = records.iter().map(|game| book.add_game(game, plies)).sum();
+        println!("{}: {} games, {} moves", file, records.len(), moves);
+    }
+    // Self-play games open from the recorded lines and extend them.
+    let recorded = book.clone();
+    for game in 0..games {
+        book.add_game(&self_play(&recorded, difficulty, seed.wrapping_add(game)), plies);
+        println!("Self-play game {}/{}", game + 1, games);
+    }
+    book.prune(min_weight);
+
+    book.save(&out)
+        .map_err(|err| format!("Cannot write '{}': {}", out, err))?;
+    println!("Wrote {} book moves to {}", book.len(), out);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        process::exit(2);
+    }
+}
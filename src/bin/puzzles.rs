@@ -0,0 +1,88 @@
+//! Mine tactics puzzles from self-play games.
+//!
+//! ```text
+//! cargo run --release --bin puzzles -- --games 50 --out puzzles/mined.txt
+//! ```
+//!
+//! The AI plays `--games <n>` (default 20) games against itself at
+//! `--level <name>` (`easy`) from `--seed <n>` (0), opening from the
+//! built-in book. Every position in which the side to move has a forced
+//! win starting with a single move becomes a puzzle (see `Puzzle::mine`),
+//! searched with at most `--max-nodes <n>` (20000) nodes per search.
+//! Positions seen before in any rotation or reflection are skipped, and
+//! puzzles rated below `--min-rating <n>` (1) are dropped. The puzzles are
+//! written easiest first to `--out <file>` (`puzzles/mined.txt`), which
+//! the console plays with `--puzzle-file <file>`.
+
+use gomoku::{
+    option_or, BookEngine, Difficulty, DifficultyEngine, Engine, Gomoku, Puzzle, SearchLimits,
+    VctLimits,
+};
+use std::collections::HashSet;
+use std::env;
+use std::process;
+
+/// Play a game with both sides at `difficulty` and return every position
+/// before a move, the starting position first.
+fn self_play(difficulty: Difficulty, seed: u64) -> Vec<Gomoku> {
+    let mut engine = BookEngine::with_builtin(Box::new(DifficultyEngine::new(difficulty, seed)));
+    engine.set_seed(seed);
+    engine.set_threads(1);
+    let mut game = Gomoku::new();
+    let mut positions = Vec::new();
+    while game.check_winner().is_none() && !game.is_board_full() {
+        positions.push(game.clone());
+        let (row, col) = engine.choose_move(&game, &SearchLimits::default()).best_move;
+        game.push_move(row, col).expect("the engine plays legal moves");
+    }
+    positions
+}
+
+fn run() -> Result<(), String> {
+    let args: Vec<String> = env::args().collect();
+    let games: u64 = option_or(&args, "--games", 20)?;
+    let level: String = option_or(&args, "--level", "easy".to_string())?;
+    let seed: u64 = option_or(&args, "--seed", 0)?;
+    let max_nodes: usize = option_or(&args, "--max-nodes", 20_000)?;
+    let min_rating: u32 = option_or(&args, "--min-rating", 1)?;
+    let out: String = option_or(&args, "--out", "puzzles/mined.txt".to_string())?;
+    let difficulty =
+        Difficulty::from_name(&level).ok_or_else(|| format!("Unknown level '{}'", level))?;
+    let limits = VctLimits {
+        max_depth: 8,
+        max_nodes,
+    };
+
+    let mut seen = HashSet::new();
+    let mut puzzles = Vec::new();
+    for index in 0..games {
+        let game_seed = seed.wrapping_add(index);
+        let mut found = 0;
+        for position in self_play(difficulty, game_seed) {
+            let name = format!("Self-play {} move {}", game_seed, position.moves().len() + 1);
+            let Some(puzzle) = Puzzle::mine(&position, &name, &limits) else {
+                continue;
+            };
+            if !seen.insert(puzzle.position.canonical().0.hash())
+                || puzzle.rating.is_some_and(|rating| rating < min_rating)
+            {
+                continue;
+            }
+            puzzles.push(puzzle);
+            found += 1;
+        }
+        println!("Game {}/{}: {} puzzles", index + 1, games, found);
+    }
+    puzzles.sort_by_key(|puzzle| puzzle.rating);
+
+    Puzzle::save_all(&out, &puzzles).map_err(|err| format!("Cannot write '{}': {}", out, err))?;
+    println!("Wrote {} puzzles to {}", puzzles.len(), out);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        process::exit(2);
+    }
+}
@@ -0,0 +1,58 @@
+//! Tune the evaluation weights from self-play games.
+//!
+//! ```text
+//! cargo run --release --bin tune -- --games 100 --out tuned.txt
+//! ```
+//!
+//! Options: `--games <n>` (default 100), `--depth <plies>` (1),
+//! `--random-plies <n>` (4), `--seed <n>` (0), `--rounds <n>` (50),
+//! `--params <file>` to start from other weights and `--out <file>`
+//! (`tuned-params.txt`). The result can be loaded with the console game's
+//! `--eval-params` option.
+
+use gomoku::{fit_scale, mean_error, option_or, tune_params, EvalParams, SelfPlay};
+use std::env;
+use std::process;
+
+fn run() -> Result<(), String> {
+    let args: Vec<String> = env::args().collect();
+    let games: u64 = option_or(&args, "--games", 100)?;
+    let rounds: usize = option_or(&args, "--rounds", 50)?;
+    let out: String = option_or(&args, "--out", "tuned-params.txt".to_string())?;
+    let config = SelfPlay {
+        depth: option_or(&args, "--depth", 1)?,
+        random_plies: option_or(&args, "--random-plies", 4)?,
+        seed: option_or(&args, "--seed", 0)?,
+    };
+    let params = match option_or(&args, "--params", String::new())? {
+        path if path.is_empty() => EvalParams::default(),
+        path => EvalParams::load(&path)
+            .map_err(|err| format!("Cannot read evaluation parameters '{}': {}", path, err))?,
+    };
+
+    let mut samples = Vec::new();
+    for game in 0..games {
+        samples.extend(config.play_game(&params, game));
+        println!("Game {}/{}: {} positions", game + 1, games, samples.len());
+    }
+
+    let scale = fit_scale(&samples, &params);
+    let start = mean_error(&samples, &params, scale);
+    println!("Scale {:.6}, error {:.6}", scale, start);
+    let (tuned, error) = tune_params(&samples, &params, scale, rounds);
+    println!("Tuned error {:.6}", error);
+    print!("{}", tuned);
+
+    tuned
+        .save(&out)
+        .map_err(|err| format!("Cannot write '{}': {}", out, err))?;
+    println!("Wrote {}", out);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        process::exit(2);
+    }
+}
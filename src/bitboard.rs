@@ -0,0 +1,373 @@
+//! Bitboard representation of the stones.
+//!
+//! Each color keeps one bitset per row, column and diagonal, where bit `i`
+//! stands for the `i`-th cell along the line. Fives, line shapes and
+//! candidate moves are found with shifts and masks instead of per-cell
+//! loops. [`Gomoku`](crate::Gomoku) keeps a bitboard in step with its cell
+//! array.
+//!
+//! Lines are identified by a direction, numbered as in
+//! [`DIRECTIONS`](crate::DIRECTIONS), and an index within that direction:
+//!
+//! * rows and columns are indexed by row and column, with bits running
+//!   left to right and top to bottom;
+//! * down-right diagonals are indexed by `row + BOARD_SIZE - 1 - col` and
+//!   up-right diagonals by `row + col`, both with bits running from the
+//!   top row down.
+
+use crate::{BOARD_SIZE, Cell, WIN_LENGTH};
+
+/// Number of diagonals in each direction, including the short ones in the
+/// corners.
+const DIAGONALS: usize = 2 * BOARD_SIZE - 1;
+
+/// Number of line ids; every direction reserves room for [`DIAGONALS`]
+/// lines even though rows and columns use only the first `BOARD_SIZE`.
+pub(crate) const LINE_IDS: usize = 4 * DIAGONALS;
+
+const FULL_LINE: u16 = (1 << BOARD_SIZE) - 1;
+
+/// Id of the line through `(row, col)` in `direction` and the bit of the
+/// cell within it.
+pub(crate) fn locate(direction: usize, row: usize, col: usize) -> (usize, usize) {
+    let (index, bit) = match direction {
+        0 => (row, col),
+        1 => (col, row),
+        2 => (row + BOARD_SIZE - 1 - col, row.min(col)),
+        _ => (row + col, row - (row + col).saturating_sub(BOARD_SIZE - 1)),
+    };
+    (direction * DIAGONALS + index, bit)
+}
+
+/// Number of cells on the line with the given id, or zero for the unused
+/// row and column ids.
+pub(crate) fn line_len(line: usize) -> usize {
+    let (direction, index) = (line / DIAGONALS, line % DIAGONALS);
+    if direction < 2 {
+        if index < BOARD_SIZE { BOARD_SIZE } else { 0 }
+    } else {
+        BOARD_SIZE - index.abs_diff(BOARD_SIZE - 1)
+    }
+}
+
+/// Ids of the lines long enough to hold a five.
+pub(crate) fn playable_lines() -> impl Iterator<Item = usize> {
+    (0..LINE_IDS).filter(|&line| line_len(line) >= WIN_LENGTH)
+}
+
+fn color_index(color: Cell) -> usize {
+    match color {
+        Cell::White => 1,
+        _ => 0,
+    }
+}
+
+/// Whether `bits` has `WIN_LENGTH` consecutive bits set.
+fn has_run(bits: u16) -> bool {
+    let mut run = bits;
+    for shift in 1..WIN_LENGTH {
+        run &= bits >> shift;
+    }
+    run != 0
+}
+
+/// Per-color bitsets of every line of the board.
+#[derive(Clone)]
+pub(crate) struct Bitboard {
+    /// Bitset of each line id, for Black and White.
+    lines: [[u16; LINE_IDS]; 2],
+}
+
+impl Bitboard {
+    /// An empty board.
+    pub(crate) fn new() -> Self {
+        Bitboard {
+            lines: [[0; LINE_IDS]; 2],
+        }
+    }
+
+    /// Put `cell` at `(row, col)`, replacing whatever was there.
+    pub(crate) fn set(&mut self, row: usize, col: usize, cell: Cell) {
+        for direction in 0..4 {
+            let (line, bit) = locate(direction, row, col);
+            for bits in &mut self.lines {
+                bits[line] &= !(1 << bit);
+            }
+            if cell != Cell::Empty {
+                self.lines[color_index(cell)][line] |= 1 << bit;
+            }
+        }
+    }
+
+    /// Stones of `color` along a line.
+    pub(crate) fn line(&self, color: Cell, line: usize) -> u16 {
+        self.lines[color_index(color)][line]
+    }
+
+    /// Stones of either color along row `row`.
+    fn occupied_row(&self, row: usize) -> u16 {
+        self.lines[0][row] | self.lines[1][row]
+    }
+
+    /// Whether `color` has five or more in a row anywhere.
+    pub(crate) fn has_five(&self, color: Cell) -> bool {
+        self.lines[color_index(color)].iter().any(|&bits| has_run(bits))
+    }
+
+    /// Whether `color` could still make five: some line has `WIN_LENGTH`
+    /// consecutive cells free of the opponent's stones.
+    pub(crate) fn can_make_five(&self, color: Cell) -> bool {
+        let opponent = &self.lines[1 - color_index(color)];
+        playable_lines().any(|line| {
+            let cells = ((1u32 << line_len(line)) - 1) as u16;
+            has_run(cells & !opponent[line])
+        })
+    }
+
+    /// Number of stones on the board.
+    pub(crate) fn stone_count(&self) -> usize {
+        (0..BOARD_SIZE)
+            .map(|row| self.occupied_row(row).count_ones() as usize)
+            .sum()
+    }
+
+    /// Occupied cells in row-major order.
+    pub(crate) fn stones(&self) -> Vec<(usize, usize)> {
+        self.cells_in(|row| self.occupied_row(row))
+    }
+
+    /// Empty cells in row-major order.
+    pub(crate) fn empty_cells(&self) -> Vec<(usize, usize)> {
+        self.cells_in(|row| !self.occupied_row(row) & FULL_LINE)
+    }
+
+    /// Empty cells within `radius` rows and columns of a stone, in
+    /// row-major order.
+    pub(crate) fn near_stones(&self, radius: usize) -> Vec<(usize, usize)> {
+        let mut spread = [0u16; BOARD_SIZE];
+        for row in 0..BOARD_SIZE {
+            let occupied = self.occupied_row(row);
+            if occupied == 0 {
+                continue;
+            }
+            let mut wide = occupied;
+            for shift in 1..=radius {
+                wide |= occupied << shift | occupied >> shift;
+            }
+            let rows = row.saturating_sub(radius)..=(row + radius).min(BOARD_SIZE - 1);
+            for near in &mut spread[rows] {
+                *near |= wide & FULL_LINE;
+            }
+        }
+        self.cells_in(|row| spread[row] & !self.occupied_row(row))
+    }
+
+    /// Cells whose bits are set in `mask(row)`, in row-major order.
+    fn cells_in(&self, mask: impl Fn(usize) -> u16) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for row in 0..BOARD_SIZE {
+            let mut bits = mask(row);
+            while bits != 0 {
+                cells.push((row, bits.trailing_zeros() as usize));
+                bits &= bits - 1;
+            }
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::{self, SHAPE_COUNT, ShapeCounts};
+    use crate::rng::Rng;
+    use crate::{DIRECTIONS, Gomoku};
+
+    type Array = [[Cell; BOARD_SIZE]; BOARD_SIZE];
+
+    /// Five detection by walking the cell array.
+    fn array_has_five(board: &Array, player: Cell) -> bool {
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                if board[row][col] != player {
+                    continue;
+                }
+                for &(dr, dc) in DIRECTIONS.iter() {
+                    let five = (0..WIN_LENGTH as i32).all(|i| {
+                        let r = row as i32 + dr * i;
+                        let c = col as i32 + dc * i;
+                        (0..BOARD_SIZE as i32).contains(&r)
+                            && (0..BOARD_SIZE as i32).contains(&c)
+                            && board[r as usize][c as usize] == player
+                    });
+                    if five {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether some five cells in a row hold no stone of `player`'s
+    /// opponent, by walking the cell array.
+    fn array_can_make_five(board: &Array, player: Cell) -> bool {
+        (0..BOARD_SIZE).any(|row| {
+            (0..BOARD_SIZE).any(|col| {
+                DIRECTIONS.iter().any(|&(dr, dc)| {
+                    (0..WIN_LENGTH as i32).all(|i| {
+                        let r = row as i32 + dr * i;
+                        let c = col as i32 + dc * i;
+                        (0..BOARD_SIZE as i32).contains(&r)
+                            && (0..BOARD_SIZE as i32).contains(&c)
+                            && board[r as usize][c as usize] != player.opponent()
+                    })
+                })
+            })
+        })
+    }
+
+    /// Candidate moves by scanning the neighborhood of every empty cell.
+    fn array_candidates(board: &Array, radius: usize) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                if board[row][col] != Cell::Empty {
+                    continue;
+                }
+                let near = (row.saturating_sub(radius)..=(row + radius).min(BOARD_SIZE - 1))
+                    .any(|r| {
+                        (col.saturating_sub(radius)..=(col + radius).min(BOARD_SIZE - 1))
+                            .any(|c| board[r][c] != Cell::Empty)
+                    });
+                if near {
+                    moves.push((row, col));
+                }
+            }
+        }
+        moves
+    }
+
+    /// Shape counts by splitting the cells of each line at the opponent's
+    /// stones.
+    fn array_shape_counts(board: &Array, player: Cell) -> ShapeCounts {
+        let mut lines: Vec<Vec<Cell>> = Vec::new();
+        for (i, row) in board.iter().enumerate() {
+            lines.push(row.to_vec());
+            lines.push(board.iter().map(|r| r[i]).collect());
+        }
+        for k in 0..DIAGONALS {
+            let rows = k.saturating_sub(BOARD_SIZE - 1)..=k.min(BOARD_SIZE - 1);
+            lines.push(rows.clone().map(|r| board[r][k - r]).collect());
+            lines.push(rows.map(|r| board[r][BOARD_SIZE - 1 - (k - r)]).collect());
+        }
+
+        let mut counts = [0; SHAPE_COUNT];
+        for line in lines {
+            let mut len = 0;
+            let mut stones = 0;
+            for cell in line {
+                if cell == player.opponent() {
+                    pattern::add_segment(&mut counts, len, stones);
+                    len = 0;
+                    stones = 0;
+                    continue;
+                }
+                if cell == player {
+                    stones |= 1 << len;
+                }
+                len += 1;
+            }
+            pattern::add_segment(&mut counts, len, stones);
+        }
+        counts
+    }
+
+    /// Fill a board and an array with the same random stones.
+    fn random_position(rng: &mut Rng, stones: usize) -> (Gomoku, Array) {
+        let mut game = Gomoku::new();
+        let mut array = [[Cell::Empty; BOARD_SIZE]; BOARD_SIZE];
+        for i in 0..stones {
+            let (row, col) = (rng.below(BOARD_SIZE), rng.below(BOARD_SIZE));
+            let color = if i % 2 == 0 { Cell::Black } else { Cell::White };
+            game.set_cell(row, col, color);
+            array[row][col] = color;
+        }
+        (game, array)
+    }
+
+    #[test]
+    /// Every cell sits at a distinct bit of one line per direction.
+    fn lines_cover_each_cell_once() {
+        for direction in 0..4 {
+            let mut seen = [[false; BOARD_SIZE]; LINE_IDS];
+            for row in 0..BOARD_SIZE {
+                for col in 0..BOARD_SIZE {
+                    let (line, bit) = locate(direction, row, col);
+                    assert_eq!(line / DIAGONALS, direction);
+                    assert!(bit < line_len(line));
+                    assert!(!seen[line][bit]);
+                    seen[line][bit] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    /// Bit operations agree with the array scans on random positions.
+    fn bitboard_matches_array() {
+        let mut rng = Rng::new(5);
+        for round in 0..200 {
+            let (game, array) = random_position(&mut rng, round % 120);
+            assert_eq!(game.board, array);
+            let stones = array.iter().flatten().filter(|&&c| c != Cell::Empty).count();
+            assert_eq!(game.bits.stone_count(), stones);
+            if stones > 0 {
+                for radius in 1..=2 {
+                    assert_eq!(game.candidate_moves(radius), array_candidates(&array, radius));
+                }
+            }
+            for player in [Cell::Black, Cell::White] {
+                assert_eq!(game.bits.has_five(player), array_has_five(&array, player));
+                assert_eq!(
+                    game.bits.can_make_five(player),
+                    array_can_make_five(&array, player)
+                );
+                assert_eq!(game.shape_counts(player), array_shape_counts(&array, player));
+            }
+            let any_five = [Cell::Black, Cell::White].map(|p| array_has_five(&array, p));
+            assert_eq!(game.check_winner().is_some(), any_five.contains(&true));
+        }
+    }
+
+    #[test]
+    /// Lines blocked by the opponent are recognized on boards where only a
+    /// few windows stay open.
+    fn blocked_lines_match_array() {
+        let mut rng = Rng::new(45);
+        let mut open = [false; 2];
+        for round in 0..100 {
+            let mut game = Gomoku::new();
+            let mut array = [[Cell::Empty; BOARD_SIZE]; BOARD_SIZE];
+            for (row, cells) in array.iter_mut().enumerate() {
+                for (col, cell) in cells.iter_mut().enumerate() {
+                    // Pairs of columns alternate colors, shifted every row,
+                    // so every window of five holds both colors.
+                    *cell = if (col / 2 + row) % 2 == 0 { Cell::Black } else { Cell::White };
+                    game.set_cell(row, col, *cell);
+                }
+            }
+            for _ in 0..round / 2 {
+                let (row, col) = (rng.below(BOARD_SIZE), rng.below(BOARD_SIZE));
+                game.set_cell(row, col, Cell::Empty);
+                array[row][col] = Cell::Empty;
+            }
+            for player in [Cell::Black, Cell::White] {
+                let expected = array_can_make_five(&array, player);
+                assert_eq!(game.bits.can_make_five(player), expected);
+                open[expected as usize] = true;
+            }
+        }
+        assert_eq!(open, [true, true]);
+    }
+}
@@ -0,0 +1,369 @@
+//! Opening book: moves worth playing in early positions, without a search.
+//!
+//! Positions are keyed by the hash of their canonical form under the
+//! board's symmetries, so a line is found however the board is rotated or
+//! mirrored. Each position lists its book moves with a weight, the number
+//! of recorded games in which the move was played, and moves are picked at
+//! random in proportion to their weights.
+//!
+//! Books are stored as `GBOK`, a format version, the entry count and then
+//! one 12-byte entry per move, sorted by key: the little-endian position
+//! key, the row and column of the move in the canonical position and its
+//! weight. The book built from `book/openings.txt` is embedded in the
+//! crate (see [`OpeningBook::builtin`]); `cargo run --bin book` builds
+//! books from game records.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::rng::Rng;
+use crate::{
+    BOARD_SIZE, Cell, Engine, Gomoku, MoveSelection, PvLine, SearchControl, SearchLimits,
+    SearchResult,
+};
+
+const BOOK_MAGIC: &[u8; 4] = b"GBOK";
+const BOOK_VERSION: u32 = 1;
+/// Size of one saved entry: key, row, column and weight.
+const ENTRY_BYTES: usize = 12;
+
+/// The book embedded in the crate.
+static BUILTIN: &[u8] = include_bytes!("../book/opening.book");
+
+/// A book move of the canonical position with key `key`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    key: u64,
+    mv: (usize, usize),
+    weight: u16,
+}
+
+/// Opening moves keyed by symmetry-normalized positions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    /// Sorted by key and move.
+    entries: Vec<Entry>,
+}
+
+impl OpeningBook {
+    /// An empty book.
+    pub fn new() -> Self {
+        OpeningBook::default()
+    }
+
+    /// The book that ships with the crate.
+    pub fn builtin() -> &'static OpeningBook {
+        static BOOK: OnceLock<OpeningBook> = OnceLock::new();
+        BOOK.get_or_init(|| {
+            OpeningBook::from_bytes(BUILTIN).expect("the embedded opening book is valid")
+        })
+    }
+
+    /// Number of moves in the book, over all positions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the book has no moves.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add `weight` to the book move `mv` of `game`'s position.
+    pub fn add(&mut self, game: &Gomoku, mv: (usize, usize), weight: u16) {
+        let (key, symmetries) = game.canonical_hash();
+        // Symmetric positions map a move to several canonical cells;
+        // always store the first of them so the weights add up.
+        let mv = symmetries
+            .iter()
+            .map(|s| s.apply(mv))
+            .min()
+            .expect("at least one symmetry maps to the canonical form");
+        match self
+            .entries
+            .binary_search_by_key(&(key, mv), |e| (e.key, e.mv))
+        {
+            Ok(i) => self.entries[i].weight = self.entries[i].weight.saturating_add(weight),
+            Err(i) => self.entries.insert(i, Entry { key, mv, weight }),
+        }
+    }
+
+    /// Add the first `plies` moves of a game record with weight one each,
+    /// leaving out the moves of the side that went on to lose. Stops at
+    /// the first illegal move and returns the number of moves added.
+    pub fn add_game(&mut self, moves: &[(usize, usize)], plies: usize) -> usize {
+        let mut game = Gomoku::new();
+        let mut legal = 0;
+        for &(row, col) in moves {
+            if game.check_winner().is_some() || game.push_move(row, col).is_err() {
+                break;
+            }
+            legal += 1;
+        }
+        let winner = game.check_winner();
+
+        let mut game = Gomoku::new();
+        let mut added = 0;
+        for &(row, col) in moves.iter().take(legal.min(plies)) {
+            if winner.is_none_or(|w| w == game.current_player()) {
+                self.add(&game, (row, col), 1);
+                added += 1;
+            }
+            game.push_move(row, col).expect("the move was legal on replay");
+        }
+        added
+    }
+
+    /// Remove the moves with a weight below `min_weight`.
+    pub fn prune(&mut self, min_weight: u16) {
+        self.entries.retain(|e| e.weight >= min_weight);
+    }
+
+    /// Book moves for the player to move in `game` with their weights,
+    /// heaviest first.
+    pub fn moves(&self, game: &Gomoku) -> Vec<((usize, usize), u16)> {
+        let (key, symmetries) = game.canonical_hash();
+        let back = symmetries[0].inverse();
+        let start = self.entries.partition_point(|e| e.key < key);
+        let mut moves: Vec<_> = self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .map(|e| (back.apply(e.mv), e.weight))
+            .filter(|&((row, col), _)| game.board[row][col] == Cell::Empty)
+            .collect();
+        moves.sort_by_key(|&(_, weight)| Reverse(weight));
+        moves
+    }
+
+    /// A book move for `game` picked at random in proportion to the
+    /// weights, or `None` when the position is not in the book.
+    pub fn choose(&self, game: &Gomoku, rng: &mut Rng) -> Option<(usize, usize)> {
+        let moves = self.moves(game);
+        let total: u64 = moves.iter().map(|&(_, w)| w as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = rng.next_u64() % total;
+        for &(mv, weight) in &moves {
+            if target < weight as u64 {
+                return Some(mv);
+            }
+            target -= weight as u64;
+        }
+        None
+    }
+
+    /// Read a book from the format described in the module docs.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        if bytes.len() < 16 || &bytes[..4] != BOOK_MAGIC {
+            return Err(invalid("not an opening book"));
+        }
+        if u32::from_le_bytes(bytes[4..8].try_into().unwrap()) != BOOK_VERSION {
+            return Err(invalid("unsupported opening book version"));
+        }
+        let count = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let data = &bytes[16..];
+        if data.len() != count.saturating_mul(ENTRY_BYTES) {
+            return Err(invalid("truncated opening book"));
+        }
+        let mut entries: Vec<Entry> = data
+            .chunks_exact(ENTRY_BYTES)
+            .map(|e| Entry {
+                key: u64::from_le_bytes(e[..8].try_into().unwrap()),
+                mv: (e[8] as usize, e[9] as usize),
+                weight: u16::from_le_bytes(e[10..12].try_into().unwrap()),
+            })
+            .collect();
+        if entries.iter().any(|e| e.mv.0 >= BOARD_SIZE || e.mv.1 >= BOARD_SIZE) {
+            return Err(invalid("opening book move off the board"));
+        }
+        entries.sort_by_key(|e| (e.key, e.mv));
+        Ok(OpeningBook { entries })
+    }
+
+    /// Write the book in the format described in the module docs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.entries.len() * ENTRY_BYTES);
+        bytes.extend_from_slice(BOOK_MAGIC);
+        bytes.extend_from_slice(&BOOK_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for e in &self.entries {
+            bytes.extend_from_slice(&e.key.to_le_bytes());
+            bytes.push(e.mv.0 as u8);
+            bytes.push(e.mv.1 as u8);
+            bytes.extend_from_slice(&e.weight.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Read a book from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        OpeningBook::from_bytes(&fs::read(path)?)
+    }
+
+    /// Write the book to a file that [`OpeningBook::load`] reads back.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+/// Plays from an opening book while the position is in it and lets
+/// another engine search afterwards.
+pub struct BookEngine {
+    book: OpeningBook,
+    engine: Box<dyn Engine + Send>,
+    rng: Rng,
+}
+
+impl BookEngine {
+    /// Play `engine` with book moves from `book` drawn with `seed`.
+    pub fn new(book: OpeningBook, engine: Box<dyn Engine + Send>, seed: u64) -> Self {
+        BookEngine {
+            book,
+            engine,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Play `engine` with the built-in book.
+    pub fn with_builtin(engine: Box<dyn Engine + Send>) -> Self {
+        BookEngine::new(OpeningBook::builtin().clone(), engine, 0)
+    }
+}
+
+impl Engine for BookEngine {
+    fn name(&self) -> &'static str {
+        self.engine.name()
+    }
+
+    fn choose_move(&mut self, game: &Gomoku, limits: &SearchLimits) -> SearchResult {
+        self.search(game, limits, &mut SearchControl::default())
+    }
+
+    fn search(
+        &mut self,
+        game: &Gomoku,
+        limits: &SearchLimits,
+        control: &mut SearchControl,
+    ) -> SearchResult {
+        let Some(best_move) = self.book.choose(game, &mut self.rng) else {
+            return self.engine.search(game, limits, control);
+        };
+        let lines = self
+            .book
+            .moves(game)
+            .into_iter()
+            .take(limits.multi_pv.unwrap_or(1))
+            .map(|(mv, weight)| PvLine {
+                score: weight as i32,
+                pv: vec![mv],
+            })
+            .collect();
+        let result = SearchResult {
+            best_move,
+            pv: vec![best_move],
+            lines,
+            ..SearchResult::default()
+        };
+        control.report(&result);
+        result
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.engine.set_threads(threads);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.engine.set_seed(seed);
+    }
+
+    fn set_selection(&mut self, selection: MoveSelection) {
+        self.engine.set_selection(selection);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symmetry::Symmetry;
+
+    fn play(moves: &[(usize, usize)]) -> Gomoku {
+        let mut game = Gomoku::new();
+        for &(row, col) in moves {
+            game.push_move(row, col).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    /// A move added in one position is found in every rotation and
+    /// reflection of it, mapped to the matching cell.
+    fn lookup_follows_symmetry() {
+        let mut book = OpeningBook::new();
+        book.add(&play(&[(7, 7), (5, 8)]), (8, 8), 3);
+        for symmetry in Symmetry::ALL {
+            let moves: Vec<_> = [(7, 7), (5, 8)].map(|mv| symmetry.apply(mv)).into();
+            let game = play(&moves);
+            assert_eq!(book.moves(&game), vec![(symmetry.apply((8, 8)), 3)], "{:?}", symmetry);
+        }
+        assert!(book.moves(&play(&[(7, 7)])).is_empty());
+    }
+
+    #[test]
+    /// Equivalent moves in a symmetric position share their weight, and
+    /// picks follow the weights.
+    fn weights_merge_and_guide_choices() {
+        let mut book = OpeningBook::new();
+        let center = play(&[(7, 7)]);
+        book.add(&center, (6, 7), 1);
+        book.add(&center, (7, 8), 1);
+        book.add(&center, (8, 8), 1);
+        assert_eq!(book.len(), 2);
+        assert_eq!(book.moves(&center)[0].1, 2);
+
+        let mut rng = Rng::new(3);
+        let direct = (0..200)
+            .filter(|_| {
+                let (row, col) = book.choose(&center, &mut rng).unwrap();
+                row == 7 || col == 7
+            })
+            .count();
+        assert!((100..170).contains(&direct), "{}", direct);
+        assert_eq!(book.choose(&Gomoku::new(), &mut rng), None);
+    }
+
+    #[test]
+    /// Books survive a round trip through bytes, bad data is rejected and
+    /// the embedded book opens in the center.
+    fn bytes_round_trip() {
+        let mut book = OpeningBook::new();
+        book.add(&Gomoku::new(), (7, 7), 9);
+        book.add(&play(&[(7, 7)]), (8, 8), 4);
+        assert_eq!(OpeningBook::from_bytes(&book.to_bytes()).unwrap(), book);
+        let bytes = book.to_bytes();
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(OpeningBook::from_bytes(b"GPNS").is_err());
+
+        let builtin = OpeningBook::builtin();
+        assert_eq!(builtin.moves(&Gomoku::new())[0].0, (7, 7));
+    }
+
+    #[test]
+    /// Game records add the winner's opening moves only.
+    fn games_add_the_winners_moves() {
+        let mut book = OpeningBook::new();
+        let black_wins = [(7, 7), (0, 0), (7, 8), (0, 2), (7, 9), (0, 4), (7, 10), (0, 6), (7, 11)];
+        assert_eq!(book.add_game(&black_wins, 4), 2);
+        assert_eq!(book.moves(&Gomoku::new()), vec![((7, 7), 1)]);
+        assert!(book.moves(&play(&black_wins[..1])).is_empty());
+
+        // Unfinished records count for both sides and stop at illegal moves.
+        assert_eq!(book.add_game(&[(7, 7), (6, 8), (6, 8), (5, 5)], 10), 2);
+        assert_eq!(book.moves(&Gomoku::new()), vec![((7, 7), 2)]);
+    }
+}
@@ -0,0 +1,39 @@
+//! Wall-clock timing that also works in the browser.
+//!
+//! `std::time::Instant` panics on `wasm32-unknown-unknown`, so the
+//! WebAssembly build reads the JavaScript clock instead.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Measures time elapsed since it was started.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+    #[cfg(target_arch = "wasm32")]
+    start: f64,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Stopwatch {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+            #[cfg(target_arch = "wasm32")]
+            start: js_sys::Date::now(),
+        }
+    }
+
+    /// Milliseconds since the stopwatch was started.
+    pub(crate) fn elapsed_ms(&self) -> u64 {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.start.elapsed().as_millis() as u64
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            (js_sys::Date::now() - self.start).max(0.0) as u64
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Named difficulty levels for the minimax AI.
+//!
+//! A level combines the search depth, a time budget, the number of moves
+//! searched at each node and a chance of deliberately playing a weaker
+//! move. Mistakes are drawn from the crate's seeded [`Rng`], so a level
+//! plays the same game again for the same seed. Only the master level has
+//! a time budget; on slow machines it may search less deeply and then
+//! depends on timing as well.
+
+use crate::engine::picked_minimax_search;
+use crate::rng::Rng;
+use crate::{
+    Engine, Gomoku, MinimaxOptions, SearchControl, SearchLimits, SearchResult, default_threads,
+};
+
+/// Names accepted by [`Difficulty::from_name`], easiest first.
+pub const DIFFICULTY_NAMES: [&str; 5] = ["beginner", "easy", "medium", "hard", "master"];
+
+/// How strongly the AI plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    Master,
+}
+
+/// The search settings behind a [`Difficulty`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DifficultySettings {
+    /// Search depth in plies.
+    pub depth: u32,
+    /// Time in milliseconds after which the search stops, even in the
+    /// middle of a depth, and plays the best move of the deepest completed
+    /// one.
+    pub time_ms: Option<u64>,
+    /// Moves searched at each node, best first by a one-ply evaluation.
+    pub width: usize,
+    /// Whether forced wins by threats are looked for before the search.
+    pub threat_search: bool,
+    /// Probability of playing a weaker move instead of the best one.
+    pub mistake_rate: f64,
+    /// How much worse than the best move a mistake may score.
+    pub mistake_margin: i32,
+    /// Whether the level opens from the built-in opening book. Weaker
+    /// levels play their own moves from the start.
+    pub book: bool,
+}
+
+impl Difficulty {
+    /// Every level, easiest first.
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Master,
+    ];
+
+    /// Lowercase name of the level, as in [`DIFFICULTY_NAMES`].
+    pub fn name(self) -> &'static str {
+        DIFFICULTY_NAMES[self as usize]
+    }
+
+    /// The level with the given [`Difficulty::name`].
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL.into_iter().find(|d| d.name() == name)
+    }
+
+    /// Search settings of the level.
+    pub fn settings(self) -> DifficultySettings {
+        let (depth, time_ms, width, threat_search, mistake_rate, mistake_margin, book) = match self {
+            Difficulty::Beginner => (1, None, 8, false, 0.5, 2000, false),
+            Difficulty::Easy => (2, None, 8, false, 0.3, 500, false),
+            Difficulty::Medium => (2, None, 12, true, 0.15, 200, false),
+            Difficulty::Hard => (4, None, 15, true, 0.05, 50, true),
+            Difficulty::Master => (6, Some(3000), 20, true, 0.0, 0, true),
+        };
+        DifficultySettings {
+            depth,
+            time_ms,
+            width,
+            threat_search,
+            mistake_rate,
+            mistake_margin,
+            book,
+        }
+    }
+}
+
+/// Minimax engine playing at a [`Difficulty`].
+///
+/// The depth and time limits passed to [`Engine::choose_move`] override
+/// those of the level.
+#[derive(Clone, Debug)]
+pub struct DifficultyEngine {
+    pub difficulty: Difficulty,
+    /// Number of threads the root moves are split across.
+    pub threads: usize,
+    rng: Rng,
+}
+
+impl DifficultyEngine {
+    /// An engine at `difficulty` whose mistakes are drawn from `seed`.
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        DifficultyEngine {
+            difficulty,
+            threads: default_threads(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Replace the best move by a random one scoring within the level's
+    /// mistake margin, if the level decides to make a mistake.
+    fn maybe_blunder(&mut self, result: &mut SearchResult, settings: &DifficultySettings) {
+        if self.rng.next_f64() >= settings.mistake_rate {
+            return;
+        }
+        let worse: Vec<_> = result
+            .evaluations
+            .iter()
+            .filter(|e| {
+                (e.row, e.col) != result.best_move && e.value >= result.score - settings.mistake_margin
+            })
+            .copied()
+            .collect();
+        if worse.is_empty() {
+            return;
+        }
+        let mistake = worse[self.rng.below(worse.len())];
+        result.play_instead(mistake);
+    }
+}
+
+impl Engine for DifficultyEngine {
+    fn name(&self) -> &'static str {
+        self.difficulty.name()
+    }
+
+    fn choose_move(&mut self, game: &Gomoku, limits: &SearchLimits) -> SearchResult {
+        self.search(game, limits, &mut SearchControl::default())
+    }
+
+    fn search(
+        &mut self,
+        game: &Gomoku,
+        limits: &SearchLimits,
+        control: &mut SearchControl,
+    ) -> SearchResult {
+        let settings = self.difficulty.settings();
+        let options = MinimaxOptions {
+            width: Some(settings.width),
+            threat_search: settings.threat_search,
+            time_ms: limits.time_ms.or(settings.time_ms),
+            ..MinimaxOptions::new(limits.depth.unwrap_or(settings.depth) as i32, self.threads)
+        };
+        picked_minimax_search(game, &options, control, limits.multi_pv.unwrap_or(1), |result| {
+            self.maybe_blunder(result, &settings)
+        })
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+    use crate::clock::Stopwatch;
+
+    fn opening() -> Gomoku {
+        let mut game = Gomoku::new();
+        for (row, col) in [(7, 7), (7, 8), (8, 8), (6, 6)] {
+            game.push_move(row, col).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    /// The master level's time budget ends the depth in progress rather
+    /// than only keeping a deeper one from starting.
+    fn master_stops_on_time() {
+        let mut engine = DifficultyEngine::new(Difficulty::Master, 0);
+        engine.set_threads(1);
+        let limits = SearchLimits {
+            time_ms: Some(300),
+            ..SearchLimits::default()
+        };
+        let game = opening();
+        let clock = Stopwatch::start();
+        let result = engine.choose_move(&game, &limits);
+        let elapsed = clock.elapsed_ms();
+        assert!(elapsed < 300 + 300, "search took {} ms", elapsed);
+        assert!(result.depth < Difficulty::Master.settings().depth);
+    }
+
+    #[test]
+    /// Names round-trip and stronger levels search deeper, make fewer
+    /// mistakes and are the ones that open from the book.
+    fn levels_grow_stronger() {
+        for pair in Difficulty::ALL.windows(2) {
+            let (weaker, stronger) = (pair[0].settings(), pair[1].settings());
+            assert!(stronger.depth >= weaker.depth);
+            assert!(stronger.width >= weaker.width);
+            assert!(stronger.mistake_rate <= weaker.mistake_rate);
+            assert!(stronger.book || !weaker.book);
+        }
+        assert!(!Difficulty::Beginner.settings().book);
+        assert!(Difficulty::Master.settings().book);
+        for name in DIFFICULTY_NAMES {
+            assert_eq!(Difficulty::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(Difficulty::from_name("impossible"), None);
+    }
+
+    #[test]
+    /// The same level and seed choose the same moves.
+    fn levels_are_reproducible() {
+        let game = opening();
+        for difficulty in [Difficulty::Beginner, Difficulty::Easy] {
+            let mut a = DifficultyEngine::new(difficulty, 9);
+            let mut b = DifficultyEngine::new(difficulty, 9);
+            for _ in 0..5 {
+                let mv = a.choose_move(&game, &SearchLimits::default()).best_move;
+                assert_eq!(b.choose_move(&game, &SearchLimits::default()).best_move, mv);
+                assert_eq!(game.board[mv.0][mv.1], Cell::Empty);
+            }
+        }
+    }
+
+    #[test]
+    /// Beginners sometimes stray from the best move; masters never do.
+    fn beginners_make_mistakes() {
+        let game = opening();
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let best = DifficultyEngine::new(Difficulty::Master, 0).choose_move(&game, &limits);
+
+        let mut beginner = DifficultyEngine::new(Difficulty::Beginner, 1);
+        let moves: Vec<_> = (0..20)
+            .map(|_| beginner.choose_move(&game, &SearchLimits::default()).best_move)
+            .collect();
+        assert!(moves.iter().any(|&mv| mv != moves[0]));
+
+        let mut master = DifficultyEngine::new(Difficulty::Master, 1);
+        for _ in 0..3 {
+            let result = master.choose_move(&game, &limits);
+            assert_eq!((result.best_move, result.pv), (best.best_move, best.pv.clone()));
+        }
+    }
+}
@@ -0,0 +1,762 @@
+//! Interchangeable AI players.
+//!
+//! Every search algorithm implements [`Engine`], so front ends can pick an
+//! engine by name and tests can pit engines against each other without
+//! knowing how each one works.
+
+use std::cmp::Reverse;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::clock::Stopwatch;
+use crate::rng::Rng;
+use crate::{BOARD_SIZE, Cell, Gomoku, MAX_DEPTH, MctsConfig, MinimaxOptions, MoveEval};
+
+/// Names accepted by [`engine_by_name`].
+pub const ENGINE_NAMES: [&str; 4] = ["minimax", "mcts", "greedy", "random"];
+
+/// Budget for a single move search. Engines ignore the limits that do not
+/// apply to them and use their own defaults for limits left as `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    /// Maximum search depth in plies.
+    pub depth: Option<u32>,
+    /// Time budget in milliseconds.
+    pub time_ms: Option<u64>,
+    /// Maximum number of positions searched, or playouts for MCTS.
+    pub nodes: Option<u64>,
+    /// Number of ranked lines reported in [`SearchResult::lines`]; one by
+    /// default.
+    pub multi_pv: Option<usize>,
+}
+
+impl SearchLimits {
+    /// Number of lines to report.
+    fn line_count(&self) -> usize {
+        self.multi_pv.unwrap_or(1)
+    }
+}
+
+/// A root move with its score and expected continuation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PvLine {
+    /// Score in the engine's own units.
+    pub score: i32,
+    /// The root move followed by the expected replies.
+    pub pv: Vec<(usize, usize)>,
+}
+
+/// The move chosen by an engine, how it rated the alternatives and what
+/// the search cost.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchResult {
+    pub best_move: (usize, usize),
+    /// Score of the best move in the engine's own units.
+    pub score: i32,
+    /// Scores of the root moves that were examined.
+    pub evaluations: Vec<MoveEval>,
+    /// Expected continuation, starting with the best move.
+    pub pv: Vec<(usize, usize)>,
+    /// Depth of the deepest completed search in plies.
+    pub depth: u32,
+    /// Positions visited, or playouts for MCTS.
+    pub nodes: u64,
+    /// Wall-clock time of the search in milliseconds.
+    pub elapsed_ms: u64,
+    /// The best root moves with their lines, best first, as many as
+    /// [`SearchLimits::multi_pv`] asks for. Minimax scores are exact, not
+    /// alpha-beta bounds. The best move may differ from the first line
+    /// when the engine deliberately varies its play.
+    pub lines: Vec<PvLine>,
+}
+
+impl SearchResult {
+    /// Search speed in nodes per second.
+    pub fn nodes_per_second(&self) -> u64 {
+        self.nodes * 1000 / self.elapsed_ms.max(1)
+    }
+
+    /// Play `eval` instead of the best move. Only the move itself is kept
+    /// of the principal variation, since the rest answers another move.
+    pub(crate) fn play_instead(&mut self, eval: MoveEval) {
+        if (eval.row, eval.col) == self.best_move {
+            return;
+        }
+        self.best_move = (eval.row, eval.col);
+        self.score = eval.value;
+        self.pv = vec![self.best_move];
+    }
+}
+
+/// One-line summary as printed after each AI move, e.g.
+/// `depth 3, score 120, 15234 nodes in 12 ms (1269500 nps), pv (7, 7) (7, 8)`.
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "depth {}, score {}, {} nodes in {} ms ({} nps), pv",
+            self.depth,
+            self.score,
+            self.nodes,
+            self.elapsed_ms,
+            self.nodes_per_second()
+        )?;
+        for &(row, col) in &self.pv {
+            write!(f, " ({}, {})", row, col)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lets the caller stop a running search and follow its progress.
+#[derive(Default)]
+pub struct SearchControl<'a> {
+    /// When the flag becomes true the search stops as soon as it can and
+    /// returns the result of the deepest completed depth. The first depth
+    /// always completes, so there is always a move.
+    pub stop: Option<&'a AtomicBool>,
+    /// Called with the result so far after each completed depth, or once
+    /// at the end by engines that do not search by depth.
+    pub progress: Option<&'a mut dyn FnMut(&SearchResult)>,
+}
+
+impl SearchControl<'_> {
+    /// Whether the caller asked the search to stop.
+    pub fn stopped(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// Pass `result` to the progress callback, if any.
+    pub fn report(&mut self, result: &SearchResult) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress(result);
+        }
+    }
+}
+
+/// How an engine picks among root moves that score (nearly) the best.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MoveSelection {
+    /// The first best move in row-major order.
+    #[default]
+    First,
+    /// A random move among those with the best score.
+    RandomTie,
+    /// A random move scoring at most `margin` below the best, chosen with
+    /// probability proportional to `exp((score - best) / temperature)`.
+    Softmax { margin: i32, temperature: f64 },
+}
+
+impl MoveSelection {
+    /// Replace the best move of `result` by a random pick among the moves
+    /// the selection allows.
+    pub(crate) fn apply(self, result: &mut SearchResult, rng: &mut Rng) {
+        let (margin, temperature) = match self {
+            MoveSelection::First => return,
+            MoveSelection::RandomTie => (0, f64::INFINITY),
+            MoveSelection::Softmax { margin, temperature } => (margin, temperature.max(1e-9)),
+        };
+        let best = result.score;
+        let choices: Vec<(MoveEval, f64)> = result
+            .evaluations
+            .iter()
+            .filter(|e| e.value >= best.saturating_sub(margin))
+            .map(|&e| (e, ((e.value - best) as f64 / temperature).exp()))
+            .collect();
+        let total: f64 = choices.iter().map(|&(_, weight)| weight).sum();
+        if choices.len() < 2 || total <= 0.0 {
+            return;
+        }
+        let mut target = rng.next_f64() * total;
+        let mut pick = choices[choices.len() - 1].0;
+        for &(eval, weight) in &choices {
+            if target < weight {
+                pick = eval;
+                break;
+            }
+            target -= weight;
+        }
+        result.play_instead(pick);
+    }
+}
+
+/// An AI player that chooses moves for the player to move.
+pub trait Engine {
+    /// Short name used to select the engine.
+    fn name(&self) -> &'static str;
+
+    /// Choose a move for `game.current_player()` within `limits`.
+    fn choose_move(&mut self, game: &Gomoku, limits: &SearchLimits) -> SearchResult;
+
+    /// Same as [`Engine::choose_move`], but stoppable and reporting
+    /// progress through `control`. Engines that cannot be interrupted
+    /// search to the end and report once.
+    fn search(
+        &mut self,
+        game: &Gomoku,
+        limits: &SearchLimits,
+        control: &mut SearchControl,
+    ) -> SearchResult {
+        let result = self.choose_move(game, limits);
+        control.report(&result);
+        result
+    }
+
+    /// Set the number of search threads. Single-threaded engines ignore it.
+    fn set_threads(&mut self, _threads: usize) {}
+
+    /// Restart the engine's random choices from `seed`. Engines without
+    /// randomness ignore it.
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// Change how moves of equal or nearly equal score are picked. Engines
+    /// that do not rank moves by score ignore it.
+    fn set_selection(&mut self, _selection: MoveSelection) {}
+}
+
+/// Number of threads used by default: one per available CPU.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Create an engine from one of the [`ENGINE_NAMES`].
+pub fn engine_by_name(name: &str) -> Option<Box<dyn Engine + Send>> {
+    match name {
+        "minimax" => Some(Box::new(MinimaxEngine::default())),
+        "mcts" => Some(Box::new(MctsEngine::new(MctsConfig::default()))),
+        "greedy" => Some(Box::new(GreedyEngine)),
+        "random" => Some(Box::new(RandomEngine::new(0))),
+        _ => None,
+    }
+}
+
+/// Whether the engine called `name` opens from the built-in opening book
+/// by default. Only the full-strength minimax does; the other engines are
+/// there for comparison and testing and play their own moves throughout.
+pub fn opens_from_book(name: &str) -> bool {
+    name == "minimax"
+}
+
+/// Run [`Gomoku::minimax_search`] and let `pick` change the move played
+/// after each completed depth, before it is reported to `control`. The
+/// result is the last reported one, so progress reports and the result
+/// always name the move that is actually played. Lines are cut to
+/// `line_count`.
+pub(crate) fn picked_minimax_search(
+    game: &Gomoku,
+    options: &MinimaxOptions,
+    control: &mut SearchControl,
+    line_count: usize,
+    mut pick: impl FnMut(&mut SearchResult),
+) -> SearchResult {
+    let stop = control.stop;
+    let mut picked = None;
+    let mut progress = |result: &SearchResult| {
+        let mut result = result.clone();
+        result.lines.truncate(line_count);
+        pick(&mut result);
+        control.report(&result);
+        picked = Some(result);
+    };
+    let mut inner = SearchControl {
+        stop,
+        progress: Some(&mut progress),
+    };
+    let result = game.minimax_search(options, &mut inner);
+    // The first depth always completes and is reported.
+    let mut picked = picked.expect("minimax_search reports at least once");
+    picked.nodes = result.nodes;
+    picked.elapsed_ms = result.elapsed_ms;
+    picked
+}
+
+/// Alpha-beta minimax with a threat-space pre-search, as used by
+/// [`Gomoku::ai_move`]. Honours the depth limit; with a time limit the
+/// search deepens one ply at a time until the time is up.
+#[derive(Clone, Debug)]
+pub struct MinimaxEngine {
+    /// Number of threads the root moves are split across. The chosen move
+    /// is the same for any thread count.
+    pub threads: usize,
+    /// How the move is picked among the best scoring ones.
+    pub selection: MoveSelection,
+    rng: Rng,
+}
+
+impl MinimaxEngine {
+    /// An engine picking moves by `selection`, with random picks drawn from
+    /// `seed`.
+    pub fn new(selection: MoveSelection, seed: u64) -> Self {
+        MinimaxEngine {
+            threads: default_threads(),
+            selection,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// A single-threaded engine that always plays the first best move.
+    pub(crate) fn single_threaded() -> Self {
+        MinimaxEngine {
+            threads: 1,
+            ..MinimaxEngine::default()
+        }
+    }
+}
+
+impl Default for MinimaxEngine {
+    fn default() -> Self {
+        MinimaxEngine::new(MoveSelection::First, 0)
+    }
+}
+
+impl Engine for MinimaxEngine {
+    fn name(&self) -> &'static str {
+        "minimax"
+    }
+
+    fn choose_move(&mut self, game: &Gomoku, limits: &SearchLimits) -> SearchResult {
+        self.search(game, limits, &mut SearchControl::default())
+    }
+
+    fn search(
+        &mut self,
+        game: &Gomoku,
+        limits: &SearchLimits,
+        control: &mut SearchControl,
+    ) -> SearchResult {
+        let depth = limits.depth.map_or(MAX_DEPTH, |d| d as i32);
+        let options = MinimaxOptions {
+            time_ms: limits.time_ms,
+            ..MinimaxOptions::new(depth, self.threads)
+        };
+        let (selection, rng) = (self.selection, &mut self.rng);
+        picked_minimax_search(game, &options, control, limits.line_count(), |result| {
+            selection.apply(result, rng)
+        })
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    fn set_selection(&mut self, selection: MoveSelection) {
+        self.selection = selection;
+    }
+}
+
+/// Monte Carlo tree search. The node limit caps the number of playouts.
+#[derive(Clone, Copy, Debug)]
+pub struct MctsEngine {
+    pub config: MctsConfig,
+}
+
+impl MctsEngine {
+    pub fn new(config: MctsConfig) -> Self {
+        MctsEngine { config }
+    }
+}
+
+impl Engine for MctsEngine {
+    fn name(&self) -> &'static str {
+        "mcts"
+    }
+
+    fn choose_move(&mut self, game: &Gomoku, limits: &SearchLimits) -> SearchResult {
+        self.search(game, limits, &mut SearchControl::default())
+    }
+
+    fn search(
+        &mut self,
+        game: &Gomoku,
+        limits: &SearchLimits,
+        control: &mut SearchControl,
+    ) -> SearchResult {
+        let mut config = self.config;
+        if let Some(nodes) = limits.nodes {
+            config.playouts = nodes as usize;
+        }
+        if limits.time_ms.is_some() {
+            config.time_limit_ms = limits.time_ms;
+        }
+        let mut result = game.mcts_search(&config, control.stop);
+        result.lines.truncate(limits.line_count());
+        control.report(&result);
+        result
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.config.seed = seed;
+    }
+}
+
+/// Plays the move with the best static evaluation one ply ahead.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GreedyEngine;
+
+impl Engine for GreedyEngine {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn choose_move(&mut self, game: &Gomoku, limits: &SearchLimits) -> SearchResult {
+        let clock = Stopwatch::start();
+        let player = game.current_player;
+        let mut probe = game.clone();
+        let mut evaluations = Vec::new();
+        let mut best: Option<MoveEval> = None;
+        for (row, col) in game.get_valid_moves() {
+            probe.set_cell(row, col, player);
+            let value = probe.evaluate(player);
+            probe.set_cell(row, col, Cell::Empty);
+            let eval = MoveEval { row, col, value };
+            evaluations.push(eval);
+            if best.is_none_or(|b| value > b.value) {
+                best = Some(eval);
+            }
+        }
+        let best = best.unwrap_or(MoveEval {
+            row: BOARD_SIZE / 2,
+            col: BOARD_SIZE / 2,
+            value: 0,
+        });
+        SearchResult {
+            best_move: (best.row, best.col),
+            score: best.value,
+            pv: vec![(best.row, best.col)],
+            depth: 1,
+            nodes: evaluations.len() as u64,
+            lines: ranked(&evaluations, limits.line_count()),
+            evaluations,
+            elapsed_ms: clock.elapsed_ms(),
+        }
+    }
+}
+
+/// The `count` best scoring moves as one-move lines, best first.
+fn ranked(evaluations: &[MoveEval], count: usize) -> Vec<PvLine> {
+    let mut sorted = evaluations.to_vec();
+    sorted.sort_by_key(|e| Reverse(e.value));
+    sorted
+        .into_iter()
+        .take(count)
+        .map(|e| PvLine {
+            score: e.value,
+            pv: vec![(e.row, e.col)],
+        })
+        .collect()
+}
+
+/// Plays a uniformly random legal move; useful as a baseline opponent.
+#[derive(Clone, Debug)]
+pub struct RandomEngine {
+    rng: Rng,
+}
+
+impl RandomEngine {
+    pub fn new(seed: u64) -> Self {
+        RandomEngine {
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Engine for RandomEngine {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    fn choose_move(&mut self, game: &Gomoku, _limits: &SearchLimits) -> SearchResult {
+        let moves = game.get_valid_moves();
+        let best_move = if moves.is_empty() {
+            (BOARD_SIZE / 2, BOARD_SIZE / 2)
+        } else {
+            moves[self.rng.below(moves.len())]
+        };
+        SearchResult {
+            best_move,
+            pv: vec![best_move],
+            lines: vec![PvLine {
+                score: 0,
+                pv: vec![best_move],
+            }],
+            ..SearchResult::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    /// Every advertised name resolves to an engine of that name, and only
+    /// minimax opens from the book.
+    fn engines_are_found_by_name() {
+        for name in ENGINE_NAMES {
+            assert_eq!(engine_by_name(name).unwrap().name(), name);
+        }
+        assert!(engine_by_name("alphazero").is_none());
+        assert!(opens_from_book("minimax"));
+        assert!(!opens_from_book("random") && !opens_from_book("greedy"));
+    }
+
+    #[test]
+    /// Seeded random engines repeat their choices and only pick empty cells.
+    fn random_engine_is_reproducible() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 7, Cell::Black);
+        let mut a = RandomEngine::new(3);
+        let mut b = RandomEngine::new(3);
+        for _ in 0..10 {
+            let mv = a.choose_move(&game, &SearchLimits::default()).best_move;
+            assert_eq!(b.choose_move(&game, &SearchLimits::default()).best_move, mv);
+            assert_eq!(game.board[mv.0][mv.1], Cell::Empty);
+        }
+    }
+
+    #[test]
+    /// Engines that look ahead complete an open four.
+    fn engines_take_immediate_win() {
+        let mut game = Gomoku::new();
+        for col in 3..7 {
+            game.set_cell(7, col, Cell::Black);
+        }
+        game.set_cell(7, 2, Cell::White);
+        let limits = SearchLimits {
+            depth: Some(1),
+            nodes: Some(200),
+            ..SearchLimits::default()
+        };
+        for mut engine in [
+            Box::new(MinimaxEngine::default()) as Box<dyn Engine>,
+            Box::new(GreedyEngine),
+            Box::new(MctsEngine::new(MctsConfig::default())),
+        ] {
+            let result = engine.choose_move(&game, &limits);
+            assert_eq!(result.best_move, (7, 7), "{}", engine.name());
+        }
+    }
+
+    #[test]
+    /// Splitting the root across threads gives the single-threaded result.
+    fn threaded_minimax_matches_single_thread() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 7, Cell::Black);
+        game.set_cell(7, 8, Cell::White);
+        game.set_cell(8, 8, Cell::Black);
+        game.switch_player();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+
+        let single = MinimaxEngine::single_threaded().choose_move(&game, &limits);
+        let mut threaded = MinimaxEngine::default();
+        threaded.set_threads(4);
+        let threaded = threaded.choose_move(&game, &limits);
+        assert_eq!(
+            SearchResult { elapsed_ms: 0, ..threaded },
+            SearchResult { elapsed_ms: 0, ..single }
+        );
+    }
+
+    #[test]
+    /// Random picks stay among the moves the selection allows.
+    fn selection_respects_margin() {
+        let evaluations: Vec<_> = [(0, 10), (1, 10), (2, 10), (3, 5), (4, -100)]
+            .iter()
+            .map(|&(col, value)| MoveEval { row: 0, col, value })
+            .collect();
+        let result = SearchResult {
+            best_move: (0, 0),
+            score: 10,
+            evaluations,
+            pv: vec![(0, 0), (1, 1)],
+            ..SearchResult::default()
+        };
+        let mut rng = Rng::new(1);
+        let picks = |selection: MoveSelection, rng: &mut Rng| {
+            let mut cols = Vec::new();
+            for _ in 0..200 {
+                let mut picked = result.clone();
+                selection.apply(&mut picked, rng);
+                if picked.best_move != result.best_move {
+                    assert_eq!(picked.pv, vec![picked.best_move]);
+                }
+                cols.push(picked.best_move.1);
+            }
+            cols.sort();
+            cols.dedup();
+            cols
+        };
+
+        assert_eq!(picks(MoveSelection::First, &mut rng), vec![0]);
+        assert_eq!(picks(MoveSelection::RandomTie, &mut rng), vec![0, 1, 2]);
+        let softmax = MoveSelection::Softmax {
+            margin: 5,
+            temperature: 5.0,
+        };
+        assert_eq!(picks(softmax, &mut rng), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    /// Randomized minimax repeats itself for a seed and varies across seeds.
+    fn seeded_minimax_is_reproducible() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let pick = |seed| {
+            MinimaxEngine::new(MoveSelection::RandomTie, seed)
+                .choose_move(&game, &limits)
+                .best_move
+        };
+        assert_eq!(pick(5), pick(5));
+        let picks: Vec<_> = (0..10).map(pick).collect();
+        assert!(picks.iter().any(|&mv| mv != picks[0]));
+    }
+
+    #[test]
+    /// Results carry a playable principal variation starting with the best
+    /// move, the requested number of ranked lines and the search
+    /// statistics.
+    fn search_reports_principal_variation() {
+        let mut game = Gomoku::new();
+        for (row, col) in [(7, 7), (7, 8), (8, 8)] {
+            game.push_move(row, col).unwrap();
+        }
+        let limits = SearchLimits {
+            depth: Some(2),
+            nodes: Some(500),
+            multi_pv: Some(3),
+            ..SearchLimits::default()
+        };
+        let mut engines: [Box<dyn Engine>; 3] = [
+            Box::new(MinimaxEngine::single_threaded()),
+            Box::new(MctsEngine::new(MctsConfig::default())),
+            Box::new(GreedyEngine),
+        ];
+        for engine in engines.iter_mut() {
+            let result = engine.choose_move(&game, &limits);
+            assert_eq!(result.pv[0], result.best_move, "{}", engine.name());
+            assert!(result.nodes > 0, "{}", engine.name());
+            assert_eq!(result.lines.len(), 3, "{}", engine.name());
+            assert_eq!(result.lines[0].pv, result.pv, "{}", engine.name());
+            let mut replay = game.clone();
+            for &(row, col) in &result.pv {
+                replay.push_move(row, col).unwrap();
+            }
+        }
+
+        let result = MinimaxEngine::single_threaded().choose_move(&game, &limits);
+        assert_eq!((result.depth, result.pv.len()), (2, 2));
+        let summary = result.to_string();
+        assert!(summary.starts_with(&format!("depth 2, score {}, ", result.score)));
+        let (row, col) = result.pv[1];
+        assert!(summary.ends_with(&format!(" ({}, {})", row, col)));
+    }
+
+    #[test]
+    /// Progress is reported once per completed depth, ending with the
+    /// returned result.
+    fn progress_reports_each_depth() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let mut reports = Vec::new();
+        let mut progress = |result: &SearchResult| reports.push(result.clone());
+        let mut control = SearchControl {
+            progress: Some(&mut progress),
+            ..SearchControl::default()
+        };
+        let result = MinimaxEngine::single_threaded().search(&game, &limits, &mut control);
+        let depths: Vec<_> = reports.iter().map(|r| r.depth).collect();
+        assert_eq!(depths, vec![1, 2]);
+        assert_eq!(reports[1].pv, result.pv);
+    }
+
+    #[test]
+    /// With a random move selection every report names the move picked
+    /// for its depth, and the last one the move that is played.
+    fn progress_reports_selected_move() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let mut played = HashSet::new();
+        for seed in 0..10 {
+            let mut reports = Vec::new();
+            let mut progress = |result: &SearchResult| reports.push(result.clone());
+            let mut control = SearchControl {
+                progress: Some(&mut progress),
+                ..SearchControl::default()
+            };
+            let mut engine = MinimaxEngine::new(MoveSelection::RandomTie, seed);
+            engine.threads = 1;
+            let result = engine.search(&game, &limits, &mut control);
+            for report in &reports {
+                assert_eq!(report.pv[0], report.best_move);
+            }
+            assert_eq!(reports[1].best_move, result.best_move);
+            assert_eq!(reports[1].pv, result.pv);
+            played.insert(result.best_move);
+        }
+        assert!(played.len() > 1);
+    }
+
+    #[test]
+    /// A stopped search returns the best move of the deepest completed
+    /// depth, and always completes the first one.
+    fn stopped_search_returns_best_so_far() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        let limits = SearchLimits {
+            depth: Some(20),
+            ..SearchLimits::default()
+        };
+
+        let stop = AtomicBool::new(true);
+        let mut control = SearchControl {
+            stop: Some(&stop),
+            ..SearchControl::default()
+        };
+        let result = MinimaxEngine::single_threaded().search(&game, &limits, &mut control);
+        assert_eq!(result.depth, 1);
+        assert_eq!(game.board[result.best_move.0][result.best_move.1], Cell::Empty);
+
+        let stop = AtomicBool::new(false);
+        let clock = Stopwatch::start();
+        let result = thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(std::time::Duration::from_millis(100));
+                stop.store(true, Ordering::Relaxed);
+            });
+            let mut control = SearchControl {
+                stop: Some(&stop),
+                ..SearchControl::default()
+            };
+            let mut engine = MinimaxEngine::default();
+            engine.search(&game, &limits, &mut control)
+        });
+        assert!(clock.elapsed_ms() < 10_000);
+        assert!((1..20).contains(&result.depth));
+        assert_eq!(result.pv[0], result.best_move);
+    }
+}
@@ -0,0 +1,220 @@
+//! Move suggestions for the human player.
+//!
+//! A hint is the move a short search would play for the side to move,
+//! with the main reason it is good. The reason is read off the threats
+//! before and after the move (see [`Gomoku::threats`]), strongest first:
+//! winning beats blocking a four, which beats making an open four, and so
+//! on down to moves that merely improve the position.
+
+use std::fmt;
+
+use crate::{
+    Difficulty, DifficultyEngine, Engine, Gomoku, OpeningBook, SearchLimits, ThreatKind, WIN_SCORE,
+};
+
+/// Search time of a hint in the console and the browser, in milliseconds.
+pub const HINT_TIME_MS: u64 = 500;
+
+/// Why a hinted move is good, strongest reason first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HintReason {
+    /// Completes five in a row.
+    Win,
+    /// Stops a four of the opponent.
+    BlockFour,
+    /// Makes an open four, which wins on the next move.
+    OpenFour,
+    /// Starts a sequence the search proved to win.
+    ForcedWin,
+    /// Makes a four the opponent has to answer.
+    Four,
+    /// Stops an open three of the opponent.
+    BlockOpenThree,
+    /// Makes an open three.
+    OpenThree,
+    /// A move from the opening book.
+    Book,
+    /// The best move by the evaluation, without a direct threat.
+    Develop,
+}
+
+impl HintReason {
+    /// Short name of the reason, e.g. `block_four`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HintReason::Win => "win",
+            HintReason::BlockFour => "block_four",
+            HintReason::OpenFour => "open_four",
+            HintReason::ForcedWin => "forced_win",
+            HintReason::Four => "four",
+            HintReason::BlockOpenThree => "block_open_three",
+            HintReason::OpenThree => "open_three",
+            HintReason::Book => "book",
+            HintReason::Develop => "develop",
+        }
+    }
+
+    /// The reason as a phrase, e.g. `blocks a four`.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            HintReason::Win => "wins the game",
+            HintReason::BlockFour => "blocks a four",
+            HintReason::OpenFour => "makes an open four",
+            HintReason::ForcedWin => "starts a forced win",
+            HintReason::Four => "makes a four",
+            HintReason::BlockOpenThree => "blocks an open three",
+            HintReason::OpenThree => "makes an open three",
+            HintReason::Book => "is a standard opening move",
+            HintReason::Develop => "improves the position",
+        }
+    }
+}
+
+/// A suggested move for the side to move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hint {
+    pub row: usize,
+    pub col: usize,
+    pub reason: HintReason,
+    /// Search score from the point of view of the side to move; zero for
+    /// book moves.
+    pub score: i32,
+}
+
+/// Formats as e.g. `(7, 8) blocks a four`.
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) {}", self.row, self.col, self.reason.explanation())
+    }
+}
+
+impl Gomoku {
+    /// Suggest a move for the side to move: a book move in the opening,
+    /// otherwise the move of the master level searched for `time_ms`
+    /// milliseconds, keeping the deepest depth completed by then.
+    pub fn hint(&self, time_ms: u64) -> Hint {
+        if let Some(&((row, col), _)) = OpeningBook::builtin().moves(self).first() {
+            return Hint {
+                row,
+                col,
+                reason: HintReason::Book,
+                score: 0,
+            };
+        }
+        let limits = SearchLimits {
+            time_ms: Some(time_ms),
+            ..SearchLimits::default()
+        };
+        let result = DifficultyEngine::new(Difficulty::Master, 0).choose_move(self, &limits);
+        let (row, col) = result.best_move;
+        Hint {
+            row,
+            col,
+            reason: self.hint_reason((row, col), result.score),
+            score: result.score,
+        }
+    }
+
+    /// Why the side to move would play `mv`, given its search score.
+    fn hint_reason(&self, mv: (usize, usize), score: i32) -> HintReason {
+        let player = self.current_player;
+        let mut after = self.clone();
+        after.set_cell(mv.0, mv.1, player);
+        if after.check_winner() == Some(player) {
+            return HintReason::Win;
+        }
+        let theirs = self.threats(player.opponent());
+        let ours = after.threats(player);
+        let blocks = |kinds: &[ThreatKind]| {
+            theirs
+                .iter()
+                .any(|t| kinds.contains(&t.kind) && t.defenses.contains(&mv))
+        };
+        let makes = |kind| ours.iter().any(|t| t.kind == kind && t.stones.contains(&mv));
+
+        if blocks(&[ThreatKind::OpenFour, ThreatKind::Four]) {
+            HintReason::BlockFour
+        } else if makes(ThreatKind::OpenFour) {
+            HintReason::OpenFour
+        } else if score >= WIN_SCORE / 2 {
+            // Won positions score about a five; nothing else comes close.
+            HintReason::ForcedWin
+        } else if makes(ThreatKind::Four) {
+            HintReason::Four
+        } else if blocks(&[ThreatKind::OpenThree]) {
+            HintReason::BlockOpenThree
+        } else if makes(ThreatKind::OpenThree) {
+            HintReason::OpenThree
+        } else {
+            HintReason::Develop
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Stopwatch;
+    use crate::{Cell, RandomEngine};
+
+    fn position(black: &[(usize, usize)], white: &[(usize, usize)]) -> Gomoku {
+        let mut game = Gomoku::new();
+        for &(row, col) in black {
+            game.set_cell(row, col, Cell::Black);
+        }
+        for &(row, col) in white {
+            game.set_cell(row, col, Cell::White);
+        }
+        game
+    }
+
+    #[test]
+    /// Hints open from the book, take wins and stop the opponent's fours.
+    fn hints_win_and_defend() {
+        let hint = Gomoku::new().hint(HINT_TIME_MS);
+        assert_eq!(((hint.row, hint.col), hint.reason), ((7, 7), HintReason::Book));
+
+        let game = position(&[(7, 3), (7, 4), (7, 5), (7, 6), (2, 2)], &[(7, 2), (3, 3), (4, 3)]);
+        let hint = game.hint(HINT_TIME_MS);
+        assert_eq!(((hint.row, hint.col), hint.reason), ((7, 7), HintReason::Win));
+        assert_eq!(hint.to_string(), "(7, 7) wins the game");
+
+        let game = position(&[(3, 2), (10, 10), (11, 12)], &[(3, 3), (3, 4), (3, 5), (3, 6)]);
+        let hint = game.hint(HINT_TIME_MS);
+        assert_eq!(((hint.row, hint.col), hint.reason), ((3, 7), HintReason::BlockFour));
+    }
+
+    #[test]
+    /// A hint in a crowded middle game returns soon after its time is up
+    /// instead of finishing the depth in progress.
+    fn hint_respects_time_limit() {
+        let mut game = Gomoku::new();
+        let mut engine = RandomEngine::new(5);
+        while game.moves().len() < 20 {
+            let (row, col) = engine.choose_move(&game, &SearchLimits::default()).best_move;
+            game.push_move(row, col).unwrap();
+        }
+        assert!(game.check_winner().is_none());
+        let clock = Stopwatch::start();
+        game.hint(HINT_TIME_MS);
+        let elapsed = clock.elapsed_ms();
+        assert!(elapsed < HINT_TIME_MS + 300, "hint took {} ms", elapsed);
+    }
+
+    #[test]
+    /// Threats made and stopped by a move are named, strongest first.
+    fn reasons_follow_threats() {
+        let game = position(&[(7, 5), (7, 6), (7, 7)], &[(2, 2), (2, 3)]);
+        assert_eq!(game.hint_reason((7, 8), 0), HintReason::OpenFour);
+        assert_eq!(game.hint_reason((7, 9), 0), HintReason::Four);
+        assert_eq!(game.hint_reason((8, 8), 0), HintReason::Develop);
+        assert_eq!(game.hint_reason((8, 8), WIN_SCORE), HintReason::ForcedWin);
+
+        let mut game = position(&[(7, 5), (7, 6)], &[(4, 4), (4, 5), (4, 6)]);
+        assert_eq!(game.hint_reason((4, 7), 0), HintReason::BlockOpenThree);
+        assert_eq!(game.hint_reason((7, 7), 0), HintReason::OpenThree);
+        game.switch_player();
+        assert_eq!(game.hint_reason((4, 7), 0), HintReason::OpenFour);
+        assert_eq!(game.hint_reason((7, 7), 0), HintReason::Develop);
+    }
+}
@@ -1,13 +1,69 @@
-use std::cmp::{max, min};
+use std::cmp::{Reverse, max, min};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 
 #[cfg(target_arch = "wasm32")]
 use js_sys;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+mod analysis;
+mod args;
+mod bitboard;
+mod book;
+mod clock;
+mod difficulty;
+mod engine;
+mod hint;
+mod mcts;
+mod params;
+mod pattern;
+mod pns;
+mod ponder;
+mod puzzle;
+mod rng;
+mod symmetry;
+mod threats;
+mod tuning;
+mod vct;
+mod zobrist;
+
+pub use analysis::{GameAnalysis, MoveAnalysis, MoveLabel};
+pub use args::{option_or, option_value};
+pub use book::{BookEngine, OpeningBook};
+pub use difficulty::{DIFFICULTY_NAMES, Difficulty, DifficultyEngine, DifficultySettings};
+pub use engine::{
+    ENGINE_NAMES, Engine, GreedyEngine, MctsEngine, MinimaxEngine, MoveSelection, PvLine,
+    RandomEngine, SearchControl, SearchLimits, SearchResult, default_threads, engine_by_name,
+    opens_from_book,
+};
+pub use hint::{HINT_TIME_MS, Hint, HintReason};
+pub use mcts::MctsConfig;
+pub use params::EvalParams;
+pub use pattern::{SHAPE_COUNT, Shape, ShapeCounts};
+pub use ponder::{BackgroundSearch, Ponder, SendEngine};
+pub use pns::{PnsLimits, PnsOutcome, PnsResult, ProofSearch, Region};
+pub use puzzle::{Puzzle, PuzzleAttempt, PuzzleGoal, PuzzleVerdict};
+pub use rng::Rng;
+pub use symmetry::Symmetry;
+pub use threats::{Threat, ThreatKind};
+pub use tuning::{Sample, SelfPlay, fit_scale, mean_error, tune_params};
+pub use vct::{VctLimits, VctResult};
+
+use bitboard::Bitboard;
+use clock::Stopwatch;
+use pattern::LineCache;
+use vct::VctInterrupt;
+
 pub const BOARD_SIZE: usize = 15;
 pub const WIN_LENGTH: usize = 5;
 const MAX_DEPTH: i32 = 3; // Limit depth for performance
+/// Score of a completed five in the evaluation.
+pub(crate) const WIN_SCORE: i32 = 100000;
+
+/// Line directions as (row, column) steps: horizontal, vertical and both
+/// diagonals.
+pub(crate) const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
@@ -26,18 +82,208 @@ pub enum Cell {
     White,
 }
 
-#[derive(Clone, Copy)]
+impl Cell {
+    /// The stone colour of the other player. `Empty` has no opponent and
+    /// is returned unchanged.
+    pub fn opponent(self) -> Cell {
+        match self {
+            Cell::Black => Cell::White,
+            Cell::White => Cell::Black,
+            Cell::Empty => Cell::Empty,
+        }
+    }
+}
+
+/// Whether a game goes on, as reported by [`Gomoku::status`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameStatus {
+    /// At least one player can still make five.
+    InProgress,
+    /// The player made five in a row.
+    Won(Cell),
+    /// Neither player can make five any more, because every line of five
+    /// cells holds stones of both colors. A full board without a five is
+    /// always drawn, but most games are dead long before.
+    Draw,
+}
+
+/// Settings of one search by [`Gomoku::minimax_search`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MinimaxOptions {
+    /// Search depth in plies.
+    pub depth: i32,
+    /// Number of threads the root moves are split across.
+    pub threads: usize,
+    /// Moves searched at each node; `None` searches every empty cell.
+    pub width: Option<usize>,
+    /// Whether forced wins are looked for with a threat-space search first.
+    pub threat_search: bool,
+    /// Time in milliseconds after which the search stops and returns the
+    /// deepest completed depth. The first depth always completes.
+    pub time_ms: Option<u64>,
+}
+
+impl MinimaxOptions {
+    /// A full-width search of `depth` plies with threat search and no time
+    /// limit.
+    pub(crate) fn new(depth: i32, threads: usize) -> Self {
+        MinimaxOptions {
+            depth,
+            threads,
+            width: None,
+            threat_search: true,
+            time_ms: None,
+        }
+    }
+}
+
+/// State shared by the nodes of one minimax search.
+struct SearchContext<'a> {
+    /// The color the AI is playing.
+    ai_player: Cell,
+    /// See [`MinimaxOptions::width`].
+    width: Option<usize>,
+    /// Number of positions visited.
+    nodes: u64,
+    /// Set by the caller to abandon the search.
+    stop: Option<&'a AtomicBool>,
+    /// Clock and the number of milliseconds after which the search is
+    /// abandoned.
+    deadline: Option<(&'a Stopwatch, u64)>,
+    /// Whether the deadline has passed.
+    out_of_time: bool,
+}
+
+/// Number of positions visited between looks at the clock.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+impl SearchContext<'_> {
+    fn new(ai_player: Cell, width: Option<usize>) -> Self {
+        SearchContext {
+            ai_player,
+            width,
+            nodes: 0,
+            stop: None,
+            deadline: None,
+            out_of_time: false,
+        }
+    }
+
+    fn stopped(&mut self) -> bool {
+        // Looking on the first node too catches the deadline between root
+        // moves, each of which is searched with a fresh context.
+        if !self.out_of_time && self.nodes % DEADLINE_INTERVAL == 1 {
+            self.out_of_time = self
+                .deadline
+                .is_some_and(|(clock, limit)| clock.elapsed_ms() >= limit);
+        }
+        self.out_of_time || self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+}
+
+/// `mv` followed by the moves of `line`.
+fn prepend(mv: (usize, usize), line: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut pv = Vec::with_capacity(line.len() + 1);
+    pv.push(mv);
+    pv.extend(line);
+    pv
+}
+
+/// Outcome of searching one root move.
+#[derive(Clone, Debug, Default)]
+struct RootLine {
+    value: i32,
+    /// Principal variation starting with the root move.
+    pv: Vec<(usize, usize)>,
+    nodes: u64,
+    /// Whether the deadline cut the search of this move short.
+    out_of_time: bool,
+}
+
+/// The search result for the root moves `moves` and their lines from a
+/// search of `depth` plies, without statistics.
+fn root_result(moves: &[(usize, usize)], mut lines: Vec<RootLine>, depth: u32) -> SearchResult {
+    let mut evaluations = Vec::with_capacity(moves.len());
+    let mut best: Option<&RootLine> = None;
+
+    for (&(row, col), line) in moves.iter().zip(lines.iter()) {
+        evaluations.push(MoveEval { row, col, value: line.value });
+        if best.is_none_or(|b| line.value > b.value) {
+            best = Some(line);
+        }
+    }
+
+    let center = (BOARD_SIZE / 2, BOARD_SIZE / 2);
+    let best_move = best.map_or(center, |b| b.pv[0]);
+    let score = best.map_or(i32::MIN, |b| b.value);
+    let pv = best.map_or_else(|| vec![center], |b| b.pv.clone());
+    // Every root move was searched with a full window, so all of the
+    // values are exact and can be ranked.
+    lines.sort_by_key(|line| Reverse(line.value));
+    SearchResult {
+        best_move,
+        score,
+        pv,
+        evaluations,
+        depth,
+        lines: lines
+            .into_iter()
+            .map(|line| PvLine {
+                score: line.value,
+                pv: line.pv,
+            })
+            .collect(),
+        ..SearchResult::default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct MoveEval {
     pub row: usize,
     pub col: usize,
     pub value: i32,
 }
 
-#[derive(Clone)]
 pub struct Gomoku {
     board: [[Cell; BOARD_SIZE]; BOARD_SIZE],
     current_player: Cell,
     last_evaluations: Vec<MoveEval>,
+    /// The stones again as per-line bitsets, kept in step with `board`.
+    bits: Bitboard,
+    /// Stones placed with [`Gomoku::make_move`] or [`Gomoku::push_move`],
+    /// oldest first.
+    history: Vec<(usize, usize)>,
+    /// Shape counts of every line, updated as stones are placed and
+    /// removed.
+    lines: LineCache,
+    /// Weights used by the evaluation.
+    params: EvalParams,
+    /// Source of the book moves picked by [`Gomoku::ai_move`].
+    rng: Rng,
+}
+
+/// Copies the position, its move history and the line cache. The
+/// evaluations recorded by the last search are scratch data and are not
+/// copied.
+impl Clone for Gomoku {
+    fn clone(&self) -> Self {
+        Gomoku {
+            board: self.board,
+            bits: self.bits.clone(),
+            current_player: self.current_player,
+            last_evaluations: Vec::new(),
+            history: self.history.clone(),
+            lines: self.lines.clone(),
+            params: self.params,
+            rng: self.rng.clone(),
+        }
+    }
+}
+
+impl Default for Gomoku {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Gomoku {
@@ -45,8 +291,13 @@ impl Gomoku {
     pub fn new() -> Self {
         Gomoku {
             board: [[Cell::Empty; BOARD_SIZE]; BOARD_SIZE],
+            bits: Bitboard::new(),
             current_player: Cell::Black,
             last_evaluations: Vec::new(),
+            history: Vec::new(),
+            lines: LineCache::new(),
+            params: EvalParams::default(),
+            rng: Rng::new(0),
         }
     }
 
@@ -88,10 +339,42 @@ impl Gomoku {
         if self.board[row][col] != Cell::Empty {
             return Err("Cell already occupied");
         }
-        self.board[row][col] = self.current_player;
+        self.set_cell(row, col, self.current_player);
+        self.history.push((row, col));
+        Ok(())
+    }
+
+    /// Place a stone for the current player and pass the turn.
+    ///
+    /// This is the make half of the make/unmake pair used by the search:
+    /// the move can be taken back with [`Gomoku::pop_move`].
+    pub fn push_move(&mut self, row: usize, col: usize) -> Result<(), &'static str> {
+        self.make_move(row, col)?;
+        self.switch_player();
         Ok(())
     }
 
+    /// Take back the most recent stone placed with [`Gomoku::make_move`] or
+    /// [`Gomoku::push_move`] and give the turn back to its owner.
+    ///
+    /// Returns the cell that was cleared, or `None` if no moves were made.
+    pub fn pop_move(&mut self) -> Option<(usize, usize)> {
+        let (row, col) = self.history.pop()?;
+        self.current_player = self.board[row][col];
+        self.set_cell(row, col, Cell::Empty);
+        Some((row, col))
+    }
+
+    /// Put `cell` at `(row, col)` without touching the turn or the move
+    /// history, keeping the bitboard and the line cache up to date.
+    ///
+    /// Every change to the board goes through here.
+    pub(crate) fn set_cell(&mut self, row: usize, col: usize, cell: Cell) {
+        self.board[row][col] = cell;
+        self.bits.set(row, col, cell);
+        self.lines.update(&self.bits, row, col);
+    }
+
     /// Toggle the current player between Black and White.
     pub fn switch_player(&mut self) {
         self.current_player = match self.current_player {
@@ -106,262 +389,402 @@ impl Gomoku {
         self.current_player
     }
 
+    /// Moves played so far, oldest first.
+    pub fn moves(&self) -> &[(usize, usize)] {
+        &self.history
+    }
+
     /// Determine if either player has achieved five in a row.
     ///
-    /// The method scans the board in all four directions starting from each
-    /// occupied cell. If a sequence of `WIN_LENGTH` stones belonging to the
-    /// same player is found, that player is returned.
+    /// Every row, column and diagonal bitset of each player is checked for
+    /// `WIN_LENGTH` consecutive stones. If such a sequence is found, that
+    /// player is returned.
     pub fn check_winner(&self) -> Option<Cell> {
-        let directions = [
-            (0, 1),  // horizontal
-            (1, 0),  // vertical
-            (1, 1),  // diagonal down-right
-            (1, -1), // diagonal down-left
-        ];
-
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
-                if self.board[row][col] == Cell::Empty {
-                    continue;
-                }
-                let player = self.board[row][col];
-
-                for &(dr, dc) in directions.iter() {
-                    let mut count = 1;
-                    for step in 1..WIN_LENGTH {
-                        let r = row as i32 + dr * step as i32;
-                        let c = col as i32 + dc * step as i32;
+        [Cell::Black, Cell::White]
+            .into_iter()
+            .find(|&player| self.bits.has_five(player))
+    }
 
-                        if r < 0 || r >= BOARD_SIZE as i32 || c < 0 || c >= BOARD_SIZE as i32 {
-                            break;
-                        }
+    /// Zobrist hash of the stones on the board and the player to move.
+    ///
+    /// Equal positions always hash equally; different positions collide
+    /// only with negligible probability.
+    pub fn hash(&self) -> u64 {
+        let mut hash = if self.current_player == Cell::White {
+            zobrist::WHITE_TO_MOVE
+        } else {
+            0
+        };
+        for (row, cells) in self.board.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                hash ^= zobrist::stone(cell, row, col);
+            }
+        }
+        hash
+    }
 
-                        if self.board[r as usize][c as usize] == player {
-                            count += 1;
-                        } else {
-                            break;
-                        }
-                    }
+    /// Whether the game is won, drawn or goes on.
+    ///
+    /// Unlike [`Gomoku::is_board_full`] this declares a draw as soon as no
+    /// row, column or diagonal has five cells in a row free of one of the
+    /// players' stones.
+    pub fn status(&self) -> GameStatus {
+        if let Some(winner) = self.check_winner() {
+            GameStatus::Won(winner)
+        } else if [Cell::Black, Cell::White]
+            .into_iter()
+            .any(|player| self.bits.can_make_five(player))
+        {
+            GameStatus::InProgress
+        } else {
+            GameStatus::Draw
+        }
+    }
 
-                    if count >= WIN_LENGTH {
-                        return Some(player);
-                    }
-                }
-            }
+    /// Whether `player` accepts the opponent's offer of a draw: always
+    /// once the game is drawn, otherwise when the evaluation favors the
+    /// opponent.
+    pub fn accepts_draw(&self, player: Cell) -> bool {
+        match self.status() {
+            GameStatus::InProgress => self.evaluate(player) < 0,
+            GameStatus::Won(_) => false,
+            GameStatus::Draw => true,
         }
-        None
     }
 
     /// Check if there are no empty cells remaining on the board.
     pub fn is_board_full(&self) -> bool {
-        self.board
-            .iter()
-            .all(|row| row.iter().all(|&cell| cell != Cell::Empty))
+        self.bits.stone_count() == BOARD_SIZE * BOARD_SIZE
     }
 
     /// Collect all empty board positions.
     fn get_valid_moves(&self) -> Vec<(usize, usize)> {
-        let mut moves = Vec::new();
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
-                if self.board[row][col] == Cell::Empty {
-                    moves.push((row, col));
-                }
-            }
+        self.bits.empty_cells()
+    }
+
+    /// Empty cells within `radius` rows and columns of an existing stone.
+    ///
+    /// On an empty board the center is the only candidate.
+    pub(crate) fn candidate_moves(&self, radius: usize) -> Vec<(usize, usize)> {
+        let mut moves = self.bits.near_stones(radius);
+        if moves.is_empty() && self.board[BOARD_SIZE / 2][BOARD_SIZE / 2] == Cell::Empty {
+            moves.push((BOARD_SIZE / 2, BOARD_SIZE / 2));
         }
         moves
     }
 
+    /// Count the shapes `player` forms along every row, column and
+    /// diagonal of the board.
+    pub fn shape_counts(&self, player: Cell) -> ShapeCounts {
+        let mut counts = [0; SHAPE_COUNT];
+        pattern::add_board(&mut counts, &self.bits, player);
+        counts
+    }
+
+    /// Weights used by the evaluation.
+    pub fn eval_params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    /// Replace the evaluation weights used by the AI.
+    pub fn set_eval_params(&mut self, params: EvalParams) {
+        self.params = params;
+    }
+
     /// Heuristic evaluation of the board from the given player's
     /// perspective.
     ///
     /// Positive scores favour the supplied player while negative scores
-    /// favour the opponent. The function looks for runs of stones with
-    /// open ends and assigns increasingly large scores as sequences grow
-    /// longer.
+    /// favour the opponent. Every line is classified into shapes such as
+    /// open threes and broken fours (see [`Shape`] and the scoring spec in
+    /// the `pattern` module), and the score is the weighted shape count of
+    /// the player minus that of the opponent.
     fn evaluate(&self, perspective: Cell) -> i32 {
-        let mut score = 0;
-        let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+        pattern::score(&self.shape_counts(perspective), &self.params)
+            - pattern::score(&self.shape_counts(perspective.opponent()), &self.params)
+    }
 
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
-                if self.board[row][col] == Cell::Empty {
-                    continue;
-                }
-                let player = self.board[row][col];
-                let player_score = if player == perspective { 1 } else { -1 };
-
-                for &(dr, dc) in directions.iter() {
-                    let mut count = 1;
-                    let mut open_ends = 0;
-
-                    // Check forward
-                    for step in 1..WIN_LENGTH {
-                        let r = row as i32 + dr * step as i32;
-                        let c = col as i32 + dc * step as i32;
-                        if r < 0 || r >= BOARD_SIZE as i32 || c < 0 || c >= BOARD_SIZE as i32 {
-                            break;
-                        }
-                        if self.board[r as usize][c as usize] == player {
-                            count += 1;
-                        } else if self.board[r as usize][c as usize] == Cell::Empty {
-                            open_ends += 1;
-                            break;
-                        } else {
-                            break;
-                        }
-                    }
-
-                    // Check backward
-                    for step in 1..WIN_LENGTH {
-                        let r = row as i32 - dr * step as i32;
-                        let c = col as i32 - dc * step as i32;
-                        if r < 0 || r >= BOARD_SIZE as i32 || c < 0 || c >= BOARD_SIZE as i32 {
-                            break;
-                        }
-                        if self.board[r as usize][c as usize] == player {
-                            count += 1;
-                        } else if self.board[r as usize][c as usize] == Cell::Empty {
-                            open_ends += 1;
-                            break;
-                        } else {
-                            break;
-                        }
-                    }
-
-                    if count >= WIN_LENGTH {
-                        score += player_score * 100000; // Winning position
-                    } else if count == 4 && open_ends >= 1 {
-                        score += player_score * 1000; // Four in a row, one open end
-                    } else if count == 3 && open_ends == 2 {
-                        score += player_score * 100; // Three in a row, two open ends
-                    } else if count == 2 && open_ends == 2 {
-                        score += player_score * 10; // Two in a row, two open ends
-                    }
-                }
-            }
+    /// Same as [`Gomoku::evaluate`] but read from the line cache in
+    /// constant time.
+    ///
+    /// Building with the `eval-check` feature compares every result against
+    /// a full evaluation.
+    fn evaluate_incremental(&self, perspective: Cell) -> i32 {
+        let value = pattern::score(self.lines.totals(perspective), &self.params)
+            - pattern::score(self.lines.totals(perspective.opponent()), &self.params);
+        if cfg!(feature = "eval-check") {
+            assert_eq!(value, self.evaluate(perspective), "line cache out of date");
         }
-        score
+        value
+    }
+
+    /// Moves searched from this position: every empty cell, or with a
+    /// `width` the cells near the stones that score best for the player to
+    /// move one ply ahead, at most `width` of them, best first.
+    fn search_moves(&mut self, width: Option<usize>) -> Vec<(usize, usize)> {
+        let Some(width) = width else {
+            return self.get_valid_moves();
+        };
+        let player = self.current_player;
+        let mut scored: Vec<_> = self
+            .candidate_moves(2)
+            .into_iter()
+            .map(|(row, col)| {
+                self.push_move(row, col).expect("candidates are empty cells");
+                let score = self.evaluate_incremental(player);
+                self.pop_move();
+                ((row, col), score)
+            })
+            .collect();
+        scored.sort_by_key(|&(_, score)| Reverse(score));
+        scored.truncate(width);
+        scored.into_iter().map(|(mv, _)| mv).collect()
     }
 
-    /// Minimax search with alpha-beta pruning.
+    /// Minimax search with alpha-beta pruning, for the current player.
+    ///
+    /// Children are visited by making and unmaking moves on `self`, so the
+    /// position is unchanged when the search returns.
     ///
     /// * `depth` limits the recursive search depth.
     /// * `alpha` and `beta` are the current bounds for pruning.
-    /// * `player` indicates whose turn it is at this node.
-    /// * `ai_player` is the color the AI is playing.
+    /// * `ctx` holds the color the AI is playing and the search statistics.
+    ///
+    /// Returns the value of the position and the principal variation from
+    /// it, which is empty at the leaves. Once `ctx.stop` is set or the
+    /// deadline has passed the search unwinds with meaningless values.
     fn minimax(
-        &self,
+        &mut self,
         depth: i32,
         alpha: i32,
         beta: i32,
-        player: Cell,
-        ai_player: Cell,
-    ) -> (i32, Option<(usize, usize)>) {
-        if depth == 0 || self.check_winner().is_some() || self.is_board_full() {
-            return (self.evaluate(ai_player), None);
+        ctx: &mut SearchContext,
+    ) -> (i32, Vec<(usize, usize)>) {
+        ctx.nodes += 1;
+        if ctx.stopped() {
+            return (0, Vec::new());
+        }
+        let ai_player = ctx.ai_player;
+        if depth == 0 || self.check_winner().is_some() {
+            return (self.evaluate_incremental(ai_player), Vec::new());
         }
 
-        let valid_moves = self.get_valid_moves();
+        let valid_moves = self.search_moves(ctx.width);
         if valid_moves.is_empty() {
-            return (self.evaluate(ai_player), None);
+            return (self.evaluate_incremental(ai_player), Vec::new());
         }
 
-        let mut best_move = None;
+        let mut pv = Vec::new();
         let mut alpha = alpha;
         let mut beta = beta;
 
-        let maximizing = player == ai_player;
+        let maximizing = self.current_player == ai_player;
         if maximizing {
             let mut max_eval = i32::MIN;
             for &(row, col) in valid_moves.iter() {
-                let mut new_game = self.clone();
-                new_game.board[row][col] = player;
-                let (eval, _) = new_game.minimax(
-                    depth - 1,
-                    alpha,
-                    beta,
-                    match player {
-                        Cell::White => Cell::Black,
-                        Cell::Black => Cell::White,
-                        Cell::Empty => Cell::Empty,
-                    },
-                    ai_player,
-                );
+                self.push_move(row, col).expect("valid moves are empty cells");
+                let (eval, line) = self.minimax(depth - 1, alpha, beta, ctx);
+                self.pop_move();
                 if eval > max_eval {
                     max_eval = eval;
-                    best_move = Some((row, col));
+                    pv = prepend((row, col), line);
                 }
                 alpha = max(alpha, eval);
                 if beta <= alpha {
                     break; // Alpha-beta pruning
                 }
             }
-            (max_eval, best_move)
+            (max_eval, pv)
         } else {
             let mut min_eval = i32::MAX;
             for &(row, col) in valid_moves.iter() {
-                let mut new_game = self.clone();
-                new_game.board[row][col] = player;
-                let (eval, _) = new_game.minimax(
-                    depth - 1,
-                    alpha,
-                    beta,
-                    match player {
-                        Cell::White => Cell::Black,
-                        Cell::Black => Cell::White,
-                        Cell::Empty => Cell::Empty,
-                    },
-                    ai_player,
-                );
+                self.push_move(row, col).expect("valid moves are empty cells");
+                let (eval, line) = self.minimax(depth - 1, alpha, beta, ctx);
+                self.pop_move();
                 if eval < min_eval {
                     min_eval = eval;
-                    best_move = Some((row, col));
+                    pv = prepend((row, col), line);
                 }
                 beta = min(beta, eval);
                 if beta <= alpha {
                     break; // Alpha-beta pruning
                 }
             }
-            (min_eval, best_move)
+            (min_eval, pv)
         }
     }
 
+    /// Restart the random choice of book moves in [`Gomoku::ai_move`] from
+    /// `seed`. Games started with the same seed open the same way.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
     /// Choose an optimal move for the AI using minimax.
     ///
-    /// Returns the board coordinates of the best move. If no move is
-    /// found (which should not happen in normal play) the center of the
-    /// board is returned as a fallback.
+    /// Positions in the built-in [`OpeningBook`] are answered with a book
+    /// move picked at random in proportion to its weight, from the seed
+    /// given to [`Gomoku::set_seed`]. Otherwise a threat-space search runs first so
+    /// forced wins by fours and threes are played directly, and the move
+    /// comes from the minimax search. Returns the board coordinates of the
+    /// best move. If no move is found (which should not happen in normal
+    /// play) the center of the board is returned as a fallback.
     pub fn ai_move(&mut self) -> (usize, usize) {
+        let mut rng = self.rng.clone();
+        let book_move = OpeningBook::builtin().choose(self, &mut rng);
+        self.rng = rng;
+        if let Some(mv) = book_move {
+            self.last_evaluations.clear();
+            return mv;
+        }
+        let result =
+            self.minimax_search(&MinimaxOptions::new(MAX_DEPTH, 1), &mut SearchControl::default());
+        self.last_evaluations = result.evaluations;
+        result.best_move
+    }
+
+    /// Root of the minimax search used by [`Gomoku::ai_move`] and
+    /// [`MinimaxEngine`], searching for the current player.
+    ///
+    /// The search deepens one ply at a time up to `options.depth`, reports
+    /// each completed depth to `control` and returns the deepest completed
+    /// one when stopped or out of time. The threat search run before it
+    /// gives up when stopped or out of time as well.
+    ///
+    /// Root moves are shared out among `options.threads` worker threads.
+    /// Each root move is searched independently, so the result does not
+    /// depend on the number of threads. The WebAssembly build always
+    /// searches on the calling thread.
+    pub(crate) fn minimax_search(
+        &self,
+        options: &MinimaxOptions,
+        control: &mut SearchControl,
+    ) -> SearchResult {
         let player = self.current_player;
-        let valid_moves = self.get_valid_moves();
-        self.last_evaluations.clear();
-
-        let mut best_eval = i32::MIN;
-        let mut best_move = None;
-
-        for &(row, col) in valid_moves.iter() {
-            let mut new_game = self.clone();
-            new_game.board[row][col] = player;
-            let (eval, _) = new_game.minimax(
-                MAX_DEPTH - 1,
-                i32::MIN,
-                i32::MAX,
-                match player {
-                    Cell::White => Cell::Black,
-                    Cell::Black => Cell::White,
-                    Cell::Empty => Cell::Empty,
-                },
-                player,
-            );
-            self.last_evaluations.push(MoveEval { row, col, value: eval });
-            if eval > best_eval {
-                best_eval = eval;
-                best_move = Some((row, col));
+        let clock = Stopwatch::start();
+        if options.threat_search {
+            // The stop flag and the time budget cut the threat search short
+            // as well, leaving the first minimax depth to find a move.
+            let interrupt = VctInterrupt {
+                stop: control.stop,
+                deadline: options.time_ms.map(|limit| (&clock, limit)),
+            };
+            let vct = self.threat_search_until(player, &VctLimits::default(), true, interrupt);
+            if let Some(line) = vct.line {
+                let result = SearchResult {
+                    best_move: line[0],
+                    score: WIN_SCORE,
+                    depth: line.len() as u32,
+                    lines: vec![PvLine {
+                        score: WIN_SCORE,
+                        pv: line.clone(),
+                    }],
+                    pv: line,
+                    nodes: vct.nodes as u64,
+                    elapsed_ms: clock.elapsed_ms(),
+                    ..SearchResult::default()
+                };
+                control.report(&result);
+                return result;
             }
         }
 
-        best_move.unwrap_or((BOARD_SIZE / 2, BOARD_SIZE / 2))
+        let valid_moves = self.clone().search_moves(options.width);
+        let mut result = SearchResult::default();
+        let mut nodes = 0;
+        for depth in 1..=options.depth.max(1) {
+            // The first depth is cheap and always completes, so that a
+            // stopped search still has a move.
+            let (stop, deadline) = if depth > 1 {
+                (control.stop, options.time_ms.map(|limit| (&clock, limit)))
+            } else {
+                (None, None)
+            };
+            let lines = self.search_root_moves(&valid_moves, depth, options, stop, deadline);
+            nodes += lines.iter().map(|line| line.nodes).sum::<u64>();
+            if depth > 1 && (control.stopped() || lines.iter().any(|line| line.out_of_time)) {
+                break;
+            }
+            result = root_result(&valid_moves, lines, depth as u32);
+            result.nodes = nodes;
+            result.elapsed_ms = clock.elapsed_ms();
+            control.report(&result);
+            if options.time_ms.is_some_and(|limit| clock.elapsed_ms() >= limit) {
+                break;
+            }
+        }
+        result.nodes = nodes;
+        result.elapsed_ms = clock.elapsed_ms();
+        result
+    }
+
+    /// Minimax value, principal variation and node count of each root
+    /// move, in the order of `moves`.
+    fn search_root_moves(
+        &self,
+        moves: &[(usize, usize)],
+        depth: i32,
+        options: &MinimaxOptions,
+        stop: Option<&AtomicBool>,
+        deadline: Option<(&Stopwatch, u64)>,
+    ) -> Vec<RootLine> {
+        let player = self.current_player;
+        // Each thread searches its own copy.
+        let working_copy = || self.clone();
+        let search = |game: &mut Gomoku, (row, col): (usize, usize)| {
+            game.push_move(row, col).expect("valid moves are empty cells");
+            let mut ctx = SearchContext {
+                stop,
+                deadline,
+                ..SearchContext::new(player, options.width)
+            };
+            let (value, line) = game.minimax(depth.max(1) - 1, i32::MIN, i32::MAX, &mut ctx);
+            game.pop_move();
+            RootLine {
+                value,
+                pv: prepend((row, col), line),
+                nodes: ctx.nodes,
+                out_of_time: ctx.out_of_time,
+            }
+        };
+
+        let threads = if cfg!(target_arch = "wasm32") {
+            1
+        } else {
+            options.threads.clamp(1, moves.len().max(1))
+        };
+        if threads == 1 {
+            let mut game = working_copy();
+            return moves.iter().map(|&mv| search(&mut game, mv)).collect();
+        }
+
+        // Workers claim root moves one at a time so that slow moves do not
+        // leave other threads idle.
+        let next = AtomicUsize::new(0);
+        let mut values = vec![RootLine::default(); moves.len()];
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut game = working_copy();
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= moves.len() {
+                                break done;
+                            }
+                            done.push((i, search(&mut game, moves[i])));
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                for (i, value) in worker.join().expect("search thread panicked") {
+                    values[i] = value;
+                }
+            }
+        });
+        values
     }
 
     /// Retrieve the evaluation for a specific board position from the last AI search.
@@ -371,12 +794,127 @@ impl Gomoku {
             .find(|e| e.row == row && e.col == col)
             .map(|e| e.value)
     }
+
+    /// The `count` best moves for the current player, best first, each with
+    /// its exact score from a `depth`-ply minimax search and the expected
+    /// continuation.
+    ///
+    /// A forced win found by the threat search is returned as the only
+    /// line.
+    pub fn analyze(&self, count: usize, depth: u32) -> Vec<PvLine> {
+        let options = MinimaxOptions::new(depth as i32, default_threads());
+        let mut lines = self.minimax_search(&options, &mut SearchControl::default()).lines;
+        lines.truncate(count);
+        lines
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub struct WasmGomoku {
     inner: Gomoku,
+    engine: Box<dyn Engine>,
+    last_search: Option<SearchResult>,
+}
+
+/// Number of ranked lines requested by [`WasmGomoku::ai_move`] for the
+/// analysis panel.
+#[cfg(target_arch = "wasm32")]
+const ANALYSIS_LINES: usize = 3;
+
+/// Set `key` of a plain JavaScript object.
+#[cfg(target_arch = "wasm32")]
+fn set_property(object: &js_sys::Object, key: &str, value: JsValue) {
+    js_sys::Reflect::set(object, &JsValue::from_str(key), &value)
+        .expect("setting a property of a plain object");
+}
+
+/// Flatten a board to one number per cell, row by row: 0 for empty, 1
+/// for Black and 2 for White.
+#[cfg(target_arch = "wasm32")]
+fn board_to_js(game: &Gomoku) -> Vec<u8> {
+    game.board
+        .iter()
+        .flat_map(|row| row.iter())
+        .map(|cell| match cell {
+            Cell::Empty => 0,
+            Cell::Black => 1,
+            Cell::White => 2,
+        })
+        .collect()
+}
+
+/// Convert moves to a JavaScript array of `[row, col]` pairs.
+#[cfg(target_arch = "wasm32")]
+fn moves_to_js(moves: &[(usize, usize)]) -> JsValue {
+    let array = js_sys::Array::new();
+    for &(row, col) in moves {
+        let mv = js_sys::Array::new();
+        mv.push(&JsValue::from_f64(row as f64));
+        mv.push(&JsValue::from_f64(col as f64));
+        array.push(&mv);
+    }
+    array.into()
+}
+
+/// Convert ranked lines to a JavaScript array of `{ score, pv }` objects.
+#[cfg(target_arch = "wasm32")]
+fn lines_to_js(lines: &[PvLine]) -> JsValue {
+    let array = js_sys::Array::new();
+    for line in lines {
+        let object = js_sys::Object::new();
+        set_property(&object, "score", JsValue::from_f64(line.score as f64));
+        set_property(&object, "pv", moves_to_js(&line.pv));
+        array.push(&object);
+    }
+    array.into()
+}
+
+/// Convert threats to a JavaScript array of objects with the fields
+/// `kind` (see [`ThreatKind::name`]), `player` (1 for Black, 2 for
+/// White), `stones` and `defenses`, both arrays of `[row, col]` pairs.
+#[cfg(target_arch = "wasm32")]
+fn threats_to_js(threats: &[Threat]) -> JsValue {
+    let array = js_sys::Array::new();
+    for threat in threats {
+        let object = js_sys::Object::new();
+        let player = if threat.player == Cell::White { 2 } else { 1 };
+        set_property(&object, "kind", JsValue::from_str(threat.kind.name()));
+        set_property(&object, "player", JsValue::from_f64(player as f64));
+        set_property(&object, "stones", moves_to_js(&threat.stones));
+        set_property(&object, "defenses", moves_to_js(&threat.defenses));
+        array.push(&object);
+    }
+    array.into()
+}
+
+/// Convert a search result to a plain JavaScript object with the fields
+/// `row`, `col`, `score`, `pv` (an array of `[row, col]` pairs), `depth`,
+/// `nodes`, `elapsed_ms`, `nps`, `lines` (see `lines_to_js`) and
+/// `evaluations` (an array of `[row, col, score]` for each root move).
+#[cfg(target_arch = "wasm32")]
+fn search_to_js(result: &SearchResult) -> JsValue {
+    let object = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| set_property(&object, key, value);
+    set("row", JsValue::from_f64(result.best_move.0 as f64));
+    set("col", JsValue::from_f64(result.best_move.1 as f64));
+    set("score", JsValue::from_f64(result.score as f64));
+    set("pv", moves_to_js(&result.pv));
+    set("depth", JsValue::from_f64(result.depth as f64));
+    set("nodes", JsValue::from_f64(result.nodes as f64));
+    set("elapsed_ms", JsValue::from_f64(result.elapsed_ms as f64));
+    set("nps", JsValue::from_f64(result.nodes_per_second() as f64));
+    set("lines", lines_to_js(&result.lines));
+    let evaluations = js_sys::Array::new();
+    for eval in &result.evaluations {
+        let entry = js_sys::Array::new();
+        entry.push(&JsValue::from_f64(eval.row as f64));
+        entry.push(&JsValue::from_f64(eval.col as f64));
+        entry.push(&JsValue::from_f64(eval.value as f64));
+        evaluations.push(&entry);
+    }
+    set("evaluations", evaluations.into());
+    object.into()
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -387,21 +925,33 @@ impl WasmGomoku {
     pub fn new() -> WasmGomoku {
         WasmGomoku {
             inner: Gomoku::new(),
+            engine: Box::new(BookEngine::with_builtin(Box::new(MinimaxEngine::default()))),
+            last_search: None,
+        }
+    }
+
+    /// Select the AI engine by name, e.g. `"minimax"` or `"mcts"`. Only
+    /// minimax opens from the built-in [`OpeningBook`] (see
+    /// [`opens_from_book`]).
+    ///
+    /// Returns `false` and keeps the current engine for unknown names.
+    pub fn set_engine(&mut self, name: &str) -> bool {
+        match engine_by_name(name) {
+            Some(engine) if opens_from_book(name) => {
+                self.engine = Box::new(BookEngine::with_builtin(engine));
+                true
+            }
+            Some(engine) => {
+                self.engine = engine;
+                true
+            }
+            None => false,
         }
     }
 
     /// Flatten the internal board to a simple array for JavaScript.
     pub fn board(&self) -> Vec<u8> {
-        self.inner
-            .board
-            .iter()
-            .flat_map(|row| row.iter())
-            .map(|cell| match cell {
-                Cell::Empty => 0,
-                Cell::Black => 1,
-                Cell::White => 2,
-            })
-            .collect()
+        board_to_js(&self.inner)
     }
 
     /// Return the active player as a numeric value used by the JS side.
@@ -418,15 +968,126 @@ impl WasmGomoku {
         self.inner.make_move(row, col).is_ok()
     }
 
+    /// Restart the AI's random choices from `seed`, so that a game can be
+    /// replayed exactly.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.engine.set_seed(seed as u64);
+    }
+
+    /// Choose how the AI picks among equally good moves: `"first"` (the
+    /// default), `"tie"` for a random one of the best moves, or
+    /// `"softmax"` for a random one within `margin` points of the best,
+    /// favoring better moves. Returns `false` for unknown names.
+    pub fn set_move_selection(&mut self, name: &str, margin: i32) -> bool {
+        let selection = match name {
+            "first" => MoveSelection::First,
+            "tie" => MoveSelection::RandomTie,
+            "softmax" => MoveSelection::Softmax {
+                margin,
+                temperature: margin.max(1) as f64 / 2.0,
+            },
+            _ => return false,
+        };
+        self.engine.set_selection(selection);
+        true
+    }
+
+    /// Let the AI play at one of the [`DIFFICULTY_NAMES`], replacing the
+    /// engine chosen with [`WasmGomoku::set_engine`]. The same `seed`
+    /// reproduces the same mistakes. Levels whose settings ask for it
+    /// open from the built-in book. Returns `false` for unknown names.
+    pub fn set_difficulty(&mut self, name: &str, seed: u32) -> bool {
+        match Difficulty::from_name(name) {
+            Some(difficulty) => {
+                let engine = Box::new(DifficultyEngine::new(difficulty, seed as u64));
+                self.engine = if difficulty.settings().book {
+                    Box::new(BookEngine::new(OpeningBook::builtin().clone(), engine, seed as u64))
+                } else {
+                    engine
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the evaluation weights with those of a JS object such as
+    /// `{ open_three: 400, split_two: 15 }`, keyed by shape name. Shapes
+    /// that are not mentioned keep their current weight.
+    ///
+    /// Returns `false` and leaves the weights unchanged if a key is not a
+    /// shape name or a value is not a number.
+    pub fn set_eval_params(&mut self, params: &JsValue) -> bool {
+        let mut updated = *self.inner.eval_params();
+        for key in js_sys::Object::keys(&js_sys::Object::from(params.clone())).iter() {
+            let shape = key.as_string().and_then(|name| Shape::from_name(&name));
+            let value = js_sys::Reflect::get(params, &key).ok().and_then(|v| v.as_f64());
+            match (shape, value) {
+                (Some(shape), Some(value)) => updated.set_weight(shape, value as i32),
+                _ => return false,
+            }
+        }
+        self.inner.set_eval_params(updated);
+        true
+    }
+
     /// Compute the AI's move and return it as a two-element JS array.
     pub fn ai_move(&mut self) -> js_sys::Array {
-        let (r, c) = self.inner.ai_move();
+        let limits = SearchLimits {
+            multi_pv: Some(ANALYSIS_LINES),
+            ..SearchLimits::default()
+        };
+        let result = self.engine.choose_move(&self.inner, &limits);
+        let (r, c) = result.best_move;
+        self.inner.last_evaluations = result.evaluations.clone();
+        self.last_search = Some(result);
         let arr = js_sys::Array::new();
         arr.push(&JsValue::from_f64(r as f64));
         arr.push(&JsValue::from_f64(c as f64));
         arr
     }
 
+    /// Compute the AI's move like [`WasmGomoku::ai_move`], calling
+    /// `progress` with a search object (see `search_to_js`) after each
+    /// completed depth, and return the final search object. The move is
+    /// not played.
+    ///
+    /// The search blocks the calling thread, so pages should run it in a
+    /// Web Worker. To cancel, terminate the worker and play the move of
+    /// the last progress report.
+    pub fn ai_search(&mut self, progress: &js_sys::Function) -> JsValue {
+        let limits = SearchLimits {
+            multi_pv: Some(ANALYSIS_LINES),
+            ..SearchLimits::default()
+        };
+        let mut report = |result: &SearchResult| {
+            // A throwing callback must not abort the search.
+            let _ = progress.call1(&JsValue::NULL, &search_to_js(result));
+        };
+        let mut control = SearchControl {
+            progress: Some(&mut report),
+            ..SearchControl::default()
+        };
+        let result = self.engine.search(&self.inner, &limits, &mut control);
+        let js = search_to_js(&result);
+        self.inner.last_evaluations = result.evaluations.clone();
+        self.last_search = Some(result);
+        js
+    }
+
+    /// Details of the last AI search as a JS object (see `search_to_js`),
+    /// or `null` before the first AI move.
+    pub fn last_search(&self) -> JsValue {
+        self.last_search.as_ref().map_or(JsValue::NULL, search_to_js)
+    }
+
+    /// Rank the `count` best moves for the player to move with the
+    /// full-strength minimax search, whatever the AI's engine or level.
+    /// Returns an array of `{ score, pv }` objects, best first.
+    pub fn analyze(&self, count: usize) -> JsValue {
+        lines_to_js(&self.inner.analyze(count, MAX_DEPTH as u32))
+    }
+
     /// Get the AI evaluation score for a given position from the last search.
     pub fn evaluation_at(&self, row: usize, col: usize) -> Option<i32> {
         self.inner.evaluation_at(row, col)
@@ -441,6 +1102,47 @@ impl WasmGomoku {
         }
     }
 
+    /// The game status for JavaScript: 0 while the game goes on, 1 or 2
+    /// when Black or White has won and 3 for a draw, which may come
+    /// before the board is full (see [`Gomoku::status`]).
+    pub fn status(&self) -> u8 {
+        match self.inner.status() {
+            GameStatus::InProgress => 0,
+            GameStatus::Won(Cell::White) => 2,
+            GameStatus::Won(_) => 1,
+            GameStatus::Draw => 3,
+        }
+    }
+
+    /// Fives, fours and open threes of `player` (1 for Black, 2 for
+    /// White), strongest first, as an array of objects described at
+    /// `threats_to_js`.
+    pub fn threats(&self, player: u8) -> JsValue {
+        let player = if player == 2 { Cell::White } else { Cell::Black };
+        threats_to_js(&self.inner.threats(player))
+    }
+
+    /// Suggest a move for the player to move within [`HINT_TIME_MS`], as a
+    /// JavaScript object with the fields `row`, `col`, `score`, `reason`
+    /// (see [`HintReason::name`]) and `explanation`, a phrase such as
+    /// `"blocks a four"`.
+    pub fn hint(&self) -> JsValue {
+        let hint = self.inner.hint(HINT_TIME_MS);
+        let object = js_sys::Object::new();
+        set_property(&object, "row", JsValue::from_f64(hint.row as f64));
+        set_property(&object, "col", JsValue::from_f64(hint.col as f64));
+        set_property(&object, "score", JsValue::from_f64(hint.score as f64));
+        set_property(&object, "reason", JsValue::from_str(hint.reason.name()));
+        set_property(&object, "explanation", JsValue::from_str(hint.reason.explanation()));
+        object.into()
+    }
+
+    /// The player to move offers a draw; returns whether the opponent,
+    /// normally the AI, accepts it (see [`Gomoku::accepts_draw`]).
+    pub fn offer_draw(&self) -> bool {
+        self.inner.accepts_draw(self.inner.current_player.opponent())
+    }
+
     /// Expose whether the board is completely filled.
     pub fn is_board_full(&self) -> bool {
         self.inner.is_board_full()
@@ -452,6 +1154,86 @@ impl WasmGomoku {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+/// Number of built-in puzzles, numbered from 0 for [`WasmPuzzle::new`].
+pub fn puzzle_count() -> usize {
+    Puzzle::builtin().len()
+}
+
+/// A built-in puzzle being solved in the browser (see [`PuzzleAttempt`]).
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct WasmPuzzle {
+    attempt: PuzzleAttempt,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl WasmPuzzle {
+    #[wasm_bindgen(constructor)]
+    /// Start built-in puzzle number `index`, wrapping around after the
+    /// last one.
+    pub fn new(index: usize) -> WasmPuzzle {
+        let puzzles = Puzzle::builtin();
+        WasmPuzzle {
+            attempt: PuzzleAttempt::new(puzzles[index % puzzles.len()].clone()),
+        }
+    }
+
+    /// Name of the puzzle.
+    pub fn name(&self) -> String {
+        self.attempt.puzzle().name.clone()
+    }
+
+    /// The task, e.g. `"win with continuous fours"`.
+    pub fn description(&self) -> String {
+        self.attempt.puzzle().goal.description().to_string()
+    }
+
+    /// The player who has to find the win: 1 for Black, 2 for White.
+    pub fn player(&self) -> u8 {
+        if self.attempt.puzzle().player() == Cell::White { 2 } else { 1 }
+    }
+
+    /// The current position, flattened like [`WasmGomoku::board`].
+    pub fn board(&self) -> Vec<u8> {
+        board_to_js(self.attempt.game())
+    }
+
+    /// Play the solver's move and return `{ verdict, reply }`, where
+    /// `verdict` is `"solved"`, `"correct"`, `"wrong"` or `"invalid"` and
+    /// `reply` the defender's answer as `[row, col]` after a correct move,
+    /// otherwise `null`. Wrong and invalid moves are not played.
+    pub fn play(&mut self, row: usize, col: usize) -> JsValue {
+        let (verdict, reply) = match self.attempt.play(row, col) {
+            Ok(PuzzleVerdict::Solved) => ("solved", JsValue::NULL),
+            Ok(PuzzleVerdict::Correct { reply }) => {
+                let (row, col) = (reply.0 as f64, reply.1 as f64);
+                let pair = js_sys::Array::of2(&JsValue::from_f64(row), &JsValue::from_f64(col));
+                ("correct", pair.into())
+            }
+            Ok(PuzzleVerdict::Wrong) => ("wrong", JsValue::NULL),
+            Err(_) => ("invalid", JsValue::NULL),
+        };
+        let object = js_sys::Object::new();
+        set_property(&object, "verdict", JsValue::from_str(verdict));
+        set_property(&object, "reply", reply);
+        object.into()
+    }
+
+    /// A winning line from the current position as an array of
+    /// `[row, col]` pairs (see [`PuzzleAttempt::solution`]).
+    pub fn solution(&self) -> JsValue {
+        moves_to_js(&self.attempt.solution())
+    }
+
+    /// Whether the puzzle has been solved.
+    pub fn is_solved(&self) -> bool {
+        self.attempt.is_solved()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -526,8 +1308,8 @@ mod tests {
     /// Scores should favor the supplied player.
     fn evaluation_respects_perspective() {
         let mut game = Gomoku::new();
-        game.board[7][5] = Cell::White;
-        game.board[7][6] = Cell::White;
+        game.set_cell(7, 5, Cell::White);
+        game.set_cell(7, 6, Cell::White);
 
         let white_score = game.evaluate(Cell::White);
         let black_score = game.evaluate(Cell::Black);
@@ -535,23 +1317,96 @@ mod tests {
         assert_eq!(white_score, -black_score);
     }
 
+    #[test]
+    /// Custom weights change the evaluation, cached or not.
+    fn evaluation_uses_eval_params() {
+        let mut game = Gomoku::new();
+        game.make_move(7, 7).unwrap();
+        game.make_move(7, 8).unwrap();
+        let mut params = EvalParams::default();
+        params.set_weight(Shape::OpenTwo, 7);
+        game.set_eval_params(params);
+        assert_eq!(game.evaluate(Cell::Black), 7);
+        assert_eq!(game.evaluate_incremental(Cell::Black), 7);
+    }
+
     #[test]
     /// Winning evaluations should outrank non-winning positions.
     fn win_scores_highest() {
         let mut four = Gomoku::new();
         for col in 0..4 {
-            four.board[0][col] = Cell::Black;
+            four.set_cell(0, col, Cell::Black);
         }
         let four_score = four.evaluate(Cell::Black);
 
         let mut five = Gomoku::new();
         for col in 0..5 {
-            five.board[0][col] = Cell::Black;
+            five.set_cell(0, col, Cell::Black);
         }
         let win_score = five.evaluate(Cell::Black);
 
         assert!(win_score > four_score);
-        assert!(win_score >= 100000);
+        assert!(win_score >= WIN_SCORE);
+    }
+
+    #[test]
+    /// Games are drawn as soon as every line of five is blocked, before
+    /// the board is full, and the side that stands worse accepts a draw.
+    fn dead_positions_are_drawn() {
+        let mut game = Gomoku::new();
+        assert_eq!(game.status(), GameStatus::InProgress);
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                let color = if (col / 2 + row) % 2 == 0 { Cell::Black } else { Cell::White };
+                game.set_cell(row, col, color);
+            }
+        }
+        for (row, col) in [(0, 0), (7, 7), (14, 14), (3, 10)] {
+            game.set_cell(row, col, Cell::Empty);
+        }
+        assert_eq!(game.status(), GameStatus::Draw);
+        assert!(!game.is_board_full());
+        assert!(game.accepts_draw(Cell::Black) && game.accepts_draw(Cell::White));
+
+        // Opening one window lets Black play on.
+        for col in 0..5 {
+            game.set_cell(4, col, Cell::Empty);
+        }
+        assert_eq!(game.status(), GameStatus::InProgress);
+        for col in 0..5 {
+            game.set_cell(4, col, Cell::Black);
+        }
+        assert_eq!(game.status(), GameStatus::Won(Cell::Black));
+        assert!(!game.accepts_draw(Cell::White));
+
+        let mut game = Gomoku::new();
+        for (row, col) in [(7, 7), (0, 0), (7, 8), (0, 14), (7, 9)] {
+            game.push_move(row, col).unwrap();
+        }
+        assert!(game.accepts_draw(Cell::White));
+        assert!(!game.accepts_draw(Cell::Black));
+    }
+
+    #[test]
+    /// The AI opens from the built-in book in the center, and the seed
+    /// varies its replies reproducibly.
+    fn ai_opens_from_book() {
+        let mut game = Gomoku::new();
+        assert_eq!(game.ai_move(), (7, 7));
+        game.push_move(7, 7).unwrap();
+        let mut replies = Vec::new();
+        for seed in 0..20 {
+            game.set_seed(seed);
+            let reply = game.ai_move();
+            assert!(reply.0.abs_diff(7) <= 1 && reply.1.abs_diff(7) <= 1, "{:?}", reply);
+            replies.push(reply);
+        }
+        assert!(replies.iter().any(|&reply| reply != replies[0]));
+
+        game.set_seed(5);
+        let first = game.ai_move();
+        game.set_seed(5);
+        assert_eq!(game.ai_move(), first);
     }
 
     #[test]
@@ -559,7 +1414,7 @@ mod tests {
     fn ai_makes_winning_move() {
         let mut game = Gomoku::new();
         for col in 0..4 {
-            game.board[0][col] = Cell::Black;
+            game.set_cell(0, col, Cell::Black);
         }
 
         let (row, col) = game.ai_move();
@@ -571,7 +1426,7 @@ mod tests {
     fn evaluate_diagonal_sequences() {
         let mut game = Gomoku::new();
         for i in 0..3 {
-            game.board[3 + i][3 + i] = Cell::Black;
+            game.set_cell(3 + i, 3 + i, Cell::Black);
         }
 
         let black_score = game.evaluate(Cell::Black);
@@ -585,7 +1440,7 @@ mod tests {
         );
         assert!(black_score > 0);
         assert_eq!(black_score, -white_score);
-        assert_eq!(black_score, 300);
+        assert_eq!(black_score, Shape::OpenThree.weight());
     }
 
     #[test]
@@ -607,4 +1462,163 @@ mod tests {
         }
         assert_eq!(game.check_winner(), Some(Cell::Black));
     }
+
+    #[test]
+    /// Popping a pushed move restores the board, the turn and the score.
+    fn push_and_pop_restore_position() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        game.push_move(7, 8).unwrap();
+        let before = game.evaluate_incremental(Cell::Black);
+
+        game.push_move(8, 8).unwrap();
+        assert_eq!(game.current_player(), Cell::White);
+        assert_eq!(game.pop_move(), Some((8, 8)));
+        assert_eq!(game.board[8][8], Cell::Empty);
+        assert_eq!(game.current_player(), Cell::Black);
+        assert_eq!(game.evaluate_incremental(Cell::Black), before);
+
+        assert!(game.push_move(7, 7).is_err());
+        game.pop_move();
+        game.pop_move();
+        assert_eq!(game.pop_move(), None);
+        assert_eq!(game.evaluate_incremental(Cell::Black), 0);
+    }
+
+    #[test]
+    /// The line cache matches a full evaluation through random games with
+    /// takebacks.
+    fn incremental_evaluation_matches_full() {
+        let mut rng = Rng::new(11);
+        let mut game = Gomoku::new();
+        for _ in 0..300 {
+            if !game.history.is_empty() && rng.below(4) == 0 {
+                game.pop_move();
+            } else {
+                let moves = game.get_valid_moves();
+                let (row, col) = moves[rng.below(moves.len())];
+                game.push_move(row, col).unwrap();
+            }
+            for player in [Cell::Black, Cell::White] {
+                assert_eq!(game.evaluate_incremental(player), game.evaluate(player));
+            }
+        }
+    }
+
+    #[test]
+    /// Overwriting and clearing cells directly keeps the cache in step.
+    fn set_cell_updates_line_cache() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 5, Cell::White);
+        game.set_cell(7, 6, Cell::White);
+        game.set_cell(7, 7, Cell::White);
+        game.set_cell(7, 6, Cell::Black);
+        game.set_cell(7, 5, Cell::Empty);
+        for player in [Cell::Black, Cell::White] {
+            assert_eq!(game.evaluate_incremental(player), game.evaluate(player));
+        }
+        assert_eq!(game.lines.totals(Cell::White), &game.shape_counts(Cell::White));
+    }
+
+    #[test]
+    /// Clones keep the position but not the last search's evaluations.
+    fn clone_skips_search_scratch() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        game.last_evaluations.push(MoveEval { row: 7, col: 8, value: 1 });
+
+        let mut copy = game.clone();
+        assert!(copy.last_evaluations.is_empty());
+        assert_eq!(copy.hash(), game.hash());
+        assert_eq!(copy.pop_move(), Some((7, 7)));
+    }
+
+    /// Minimax as it was written before make/unmake: every child is a clone
+    /// of its parent and leaves rescan the whole board.
+    fn cloning_minimax(
+        game: &Gomoku,
+        depth: i32,
+        mut alpha: i32,
+        mut beta: i32,
+        player: Cell,
+        ai_player: Cell,
+        nodes: &mut u64,
+    ) -> i32 {
+        *nodes += 1;
+        if depth == 0 || game.check_winner().is_some() || game.is_board_full() {
+            return game.evaluate(ai_player);
+        }
+        let maximizing = player == ai_player;
+        let mut best = if maximizing { i32::MIN } else { i32::MAX };
+        for (row, col) in game.get_valid_moves() {
+            let mut child = game.clone();
+            child.set_cell(row, col, player);
+            let eval =
+                cloning_minimax(&child, depth - 1, alpha, beta, player.opponent(), ai_player, nodes);
+            if maximizing {
+                best = max(best, eval);
+                alpha = max(alpha, eval);
+            } else {
+                best = min(best, eval);
+                beta = min(beta, eval);
+            }
+            if beta <= alpha {
+                break;
+            }
+        }
+        best
+    }
+
+    #[test]
+    /// Analysis lines are ranked and their scores match a full-window
+    /// search of each move.
+    fn analysis_scores_are_exact() {
+        let mut game = Gomoku::new();
+        for (row, col) in [(7, 7), (7, 8), (8, 8)] {
+            game.push_move(row, col).unwrap();
+        }
+        let player = game.current_player();
+        let lines = game.analyze(3, 2);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        for line in &lines {
+            let (row, col) = line.pv[0];
+            let mut child = game.clone();
+            child.set_cell(row, col, player);
+            let mut nodes = 0;
+            let expected =
+                cloning_minimax(&child, 1, i32::MIN, i32::MAX, player.opponent(), player, &mut nodes);
+            assert_eq!(line.score, expected);
+            assert_eq!(line.pv.len(), 2);
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with `cargo test --release -- --ignored --nocapture`"]
+    /// Report nodes per second of the cloning search and of the make/unmake
+    /// search, which must visit the same tree.
+    fn bench_make_unmake() {
+        let mut game = Gomoku::new();
+        for (row, col) in [(7, 7), (7, 8), (8, 8), (6, 6), (8, 6)] {
+            game.push_move(row, col).unwrap();
+        }
+        let player = game.current_player();
+        let depth = 3;
+
+        let mut before = 0;
+        let clock = Stopwatch::start();
+        let expected = cloning_minimax(&game, depth, i32::MIN, i32::MAX, player, player, &mut before);
+        let before_ms = clock.elapsed_ms().max(1);
+
+        let mut ctx = SearchContext::new(player, None);
+        let clock = Stopwatch::start();
+        let (value, _) = game.minimax(depth, i32::MIN, i32::MAX, &mut ctx);
+        let after_ms = clock.elapsed_ms().max(1);
+        let after = ctx.nodes;
+
+        assert_eq!(value, expected);
+        assert_eq!(after, before);
+        println!("clone per child: {before} nodes, {} nodes/s", before * 1000 / before_ms);
+        println!("push/pop:        {after} nodes, {} nodes/s", after * 1000 / after_ms);
+    }
 }
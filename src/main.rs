@@ -1,12 +1,340 @@
-use gomoku::{Gomoku, Cell, BOARD_SIZE};
+use gomoku::{
+    engine_by_name, opens_from_book, option_value, BookEngine, Cell, Difficulty,
+    DifficultyEngine, EvalParams, GameStatus, Gomoku, MoveSelection, OpeningBook, Ponder,
+    Puzzle, PuzzleAttempt, PuzzleVerdict, SearchLimits, SearchResult, SendEngine, BOARD_SIZE,
+    DIFFICULTY_NAMES, ENGINE_NAMES, HINT_TIME_MS,
+};
+use std::env;
+use std::fs;
 use std::io;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seed from `--seed <n>`, or from the clock so that every game differs.
+fn seed_from_args(args: &[String]) -> Result<u64, String> {
+    match option_value(args, "--seed")? {
+        Some(seed) => seed
+            .parse()
+            .map_err(|_| format!("Invalid seed '{}'", seed)),
+        None => Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)),
+    }
+}
+
+/// Move selection from `--pick <first|tie|softmax>`, where softmax picks
+/// among the moves within `--margin <points>` (50 by default) of the best.
+fn selection_from_args(args: &[String]) -> Result<Option<MoveSelection>, String> {
+    let margin: i32 = match option_value(args, "--margin")? {
+        Some(margin) => margin
+            .parse()
+            .map_err(|_| format!("Invalid margin '{}'", margin))?,
+        None => 50,
+    };
+    match option_value(args, "--pick")?.as_deref() {
+        None => Ok(None),
+        Some("first") => Ok(Some(MoveSelection::First)),
+        Some("tie") => Ok(Some(MoveSelection::RandomTie)),
+        Some("softmax") => Ok(Some(MoveSelection::Softmax {
+            margin,
+            temperature: margin.max(1) as f64 / 2.0,
+        })),
+        Some(other) => Err(format!(
+            "Unknown pick '{}' (expected one of: first, tie, softmax)",
+            other
+        )),
+    }
+}
+
+/// Create the AI engine from the command line options:
+/// `--engine <name>` (minimax by default) or `--level <difficulty>`,
+/// `--seed <n>` for the engine's random choices, `--pick` and `--margin`
+/// for choosing among equally good moves, and `--threads <count>`. The
+/// full-strength engines (minimax, and the hard and master levels) open
+/// from the built-in book and the others from none. `--book <file>` gives
+/// any engine that book instead and `--no-book` takes the book away.
+fn engine_from_args() -> Result<SendEngine, String> {
+    let args: Vec<String> = env::args().collect();
+    let (mut engine, builtin_book): (SendEngine, bool) = if let Some(level) =
+        option_value(&args, "--level")?
+    {
+        if option_value(&args, "--engine")?.is_some() {
+            return Err("--level cannot be combined with --engine".to_string());
+        }
+        let difficulty = Difficulty::from_name(&level).ok_or_else(|| {
+            format!(
+                "Unknown level '{}' (expected one of: {})",
+                level,
+                DIFFICULTY_NAMES.join(", ")
+            )
+        })?;
+        println!("Playing at level {}", level);
+        (Box::new(DifficultyEngine::new(difficulty, 0)), difficulty.settings().book)
+    } else {
+        let name = option_value(&args, "--engine")?.unwrap_or_else(|| "minimax".to_string());
+        let engine = engine_by_name(&name).ok_or_else(|| {
+            format!(
+                "Unknown engine '{}' (expected one of: {})",
+                name,
+                ENGINE_NAMES.join(", ")
+            )
+        })?;
+        (engine, opens_from_book(&name))
+    };
+    if let Some(path) = option_value(&args, "--book")? {
+        let book = OpeningBook::load(&path)
+            .map_err(|err| format!("Cannot read opening book '{}': {}", path, err))?;
+        engine = Box::new(BookEngine::new(book, engine, 0));
+    } else if builtin_book && !args.iter().any(|a| a == "--no-book") {
+        engine = Box::new(BookEngine::with_builtin(engine));
+    }
+    if let Some(selection) = selection_from_args(&args)? {
+        engine.set_selection(selection);
+    }
+    let seed = seed_from_args(&args)?;
+    engine.set_seed(seed);
+    if let Some(threads) = option_value(&args, "--threads")? {
+        let threads: usize = threads
+            .parse()
+            .map_err(|_| format!("Invalid thread count '{}'", threads))?;
+        engine.set_threads(threads);
+    }
+    println!("AI seed: {}", seed);
+    Ok(engine)
+}
+
+/// Search limits from `--time <ms>`, the time after which the AI stops
+/// searching and plays the best move of the deepest completed depth.
+fn limits_from_args() -> Result<SearchLimits, String> {
+    let args: Vec<String> = env::args().collect();
+    let time_ms = match option_value(&args, "--time")? {
+        Some(time) => Some(
+            time.parse()
+                .map_err(|_| format!("Invalid time '{}'", time))?,
+        ),
+        None => None,
+    };
+    Ok(SearchLimits {
+        time_ms,
+        ..SearchLimits::default()
+    })
+}
+
+/// The AI engine, which is lent to a background search while the AI
+/// ponders on the human's time.
+enum Ai {
+    Ready(SendEngine),
+    Pondering(Ponder),
+}
+
+impl Ai {
+    /// Search the AI's move in `game`, reusing the ponder search if it
+    /// predicted the human's last move.
+    fn think(self, game: &Gomoku, limits: &SearchLimits) -> (SendEngine, SearchResult) {
+        let mut engine = match self {
+            Ai::Ready(engine) => engine,
+            Ai::Pondering(ponder) => {
+                let reply = *game.moves().last().expect("the human has moved");
+                match ponder.finish(reply) {
+                    (engine, Some(result)) => {
+                        println!("Ponder hit");
+                        return (engine, result);
+                    }
+                    (engine, None) => engine,
+                }
+            }
+        };
+        let result = engine.choose_move(game, limits);
+        (engine, result)
+    }
+}
+
+/// Let the AI choose and play its move, then start pondering on the
+/// predicted reply if `ponder` is set.
+fn ai_turn(ai: Ai, game: &mut Gomoku, limits: &SearchLimits, ponder: bool) -> Ai {
+    println!("AI ({:?}) is thinking...", game.current_player());
+    let (engine, result) = ai.think(game, limits);
+    let (row, col) = result.best_move;
+    println!("AI moves to ({}, {})", row, col);
+    println!("{}", result);
+    game.make_move(row, col).expect("AI made an invalid move");
+    if !ponder {
+        return Ai::Ready(engine);
+    }
+    let mut next = game.clone();
+    next.switch_player();
+    match Ponder::start(engine, &next, &result, *limits) {
+        Ok(ponder) => Ai::Pondering(ponder),
+        Err(engine) => Ai::Ready(engine),
+    }
+}
+
+/// Evaluation weights from the file given with `--eval-params <file>`, or
+/// the defaults.
+fn eval_params_from_args() -> Result<EvalParams, String> {
+    let args: Vec<String> = env::args().collect();
+    match option_value(&args, "--eval-params")? {
+        Some(path) => EvalParams::load(&path)
+            .map_err(|err| format!("Cannot read evaluation parameters '{}': {}", path, err)),
+        None => Ok(EvalParams::default()),
+    }
+}
+
+/// Print the final board and the result if the game has ended, by five
+/// in a row or because neither side can make five any more.
+fn game_over(game: &Gomoku, human_color: Cell) -> bool {
+    let result = match game.status() {
+        GameStatus::InProgress => return false,
+        GameStatus::Won(winner) if winner == human_color => format!("You win ({:?})!", winner),
+        GameStatus::Won(winner) => format!("AI wins ({:?})!", winner),
+        GameStatus::Draw if game.is_board_full() => "Game is a draw!".to_string(),
+        GameStatus::Draw => "Game is a draw: neither side can make five any more.".to_string(),
+    };
+    game.print_board();
+    println!("{}", result);
+    true
+}
+
+/// List the fives, fours and open threes of both players with the cells
+/// that stop them.
+fn print_threats(game: &Gomoku) {
+    let threats: Vec<_> = [Cell::Black, Cell::White]
+        .into_iter()
+        .flat_map(|player| game.threats(player))
+        .collect();
+    if threats.is_empty() {
+        println!("No threats on the board.");
+    }
+    for threat in threats {
+        println!("{}", threat);
+    }
+}
+
+/// Search depth of the post-game analysis.
+const ANALYSIS_DEPTH: u32 = 3;
+
+/// After the game, print a report of every move's quality with
+/// `--analyze`, and save the game annotated the same way as SGF with
+/// `--sgf <file>`.
+fn analyze_finished_game(game: &Gomoku) -> Result<(), String> {
+    let args: Vec<String> = env::args().collect();
+    let sgf = option_value(&args, "--sgf")?;
+    let report = args.iter().any(|a| a == "--analyze");
+    if sgf.is_none() && !report {
+        return Ok(());
+    }
+    println!("Analyzing the game...");
+    let analysis = game.analyze_game(ANALYSIS_DEPTH);
+    if report {
+        print!("{}", analysis);
+    }
+    if let Some(path) = sgf {
+        fs::write(&path, analysis.to_sgf())
+            .map_err(|err| format!("Cannot write '{}': {}", path, err))?;
+        println!("Saved the annotated game to {}", path);
+    }
+    Ok(())
+}
+
+/// Puzzles from the file given with `--puzzle-file <file>`, or the
+/// built-in ones.
+fn puzzles_from_args(args: &[String]) -> Result<Vec<Puzzle>, String> {
+    match option_value(args, "--puzzle-file")? {
+        Some(path) => Puzzle::load_all(&path)
+            .map_err(|err| format!("Cannot read puzzles '{}': {}", path, err)),
+        None => Ok(Puzzle::builtin().to_vec()),
+    }
+}
+
+/// Format moves as `(row, col)` pairs.
+fn format_moves(moves: &[(usize, usize)]) -> String {
+    let moves: Vec<String> = moves.iter().map(|(row, col)| format!("({}, {})", row, col)).collect();
+    moves.join(" ")
+}
+
+/// Work through `puzzles` in order, checking each move and playing the
+/// defender's replies.
+fn puzzle_mode(puzzles: &[Puzzle]) {
+    println!("Gomoku puzzles: enter moves as 'row col', 'solution' to see a winning line,");
+    println!("'next' to skip to the next puzzle or 'quit' to stop.");
+    let mut solved = 0;
+    'puzzles: for (number, puzzle) in puzzles.iter().enumerate() {
+        println!();
+        let rating = puzzle.rating.map_or(String::new(), |r| format!(" (rating {})", r));
+        println!("Puzzle {} of {}: {}{}", number + 1, puzzles.len(), puzzle.name, rating);
+        println!("{:?} to move and {}.", puzzle.player(), puzzle.goal.description());
+        let mut attempt = PuzzleAttempt::new(puzzle.clone());
+        loop {
+            attempt.game().print_board();
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input).expect("Failed to read input") == 0 {
+                break 'puzzles;
+            }
+            match input.trim().to_ascii_lowercase().as_str() {
+                "quit" => break 'puzzles,
+                "next" => continue 'puzzles,
+                "solution" => {
+                    println!("Solution: {}", format_moves(&attempt.solution()));
+                    continue;
+                }
+                _ => {}
+            }
+            let coords: Vec<usize> = input
+                .split_whitespace()
+                .filter_map(|s| s.parse().ok())
+                .collect();
+            if coords.len() != 2 {
+                println!("Invalid input. Please enter two numbers (row col).");
+                continue;
+            }
+            match attempt.play(coords[0], coords[1]) {
+                Ok(PuzzleVerdict::Solved) => {
+                    attempt.game().print_board();
+                    println!("Solved!");
+                    solved += 1;
+                    continue 'puzzles;
+                }
+                Ok(PuzzleVerdict::Correct { reply }) => println!(
+                    "Correct. {:?} answers at ({}, {}).",
+                    puzzle.player().opponent(),
+                    reply.0,
+                    reply.1
+                ),
+                Ok(PuzzleVerdict::Wrong) => println!("That does not win. Try again."),
+                Err(err) => println!("Invalid move: {}", err),
+            }
+        }
+    }
+    println!("Solved {} of {} puzzles.", solved, puzzles.len());
+}
 
 /// Entry point for the console version of the game.
 ///
 /// Handles the game loop, user input and AI moves while printing the
-/// board after each turn.
+/// board after each turn. With `--puzzle` or `--puzzle-file <file>` it
+/// runs the puzzle mode instead.
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|a| a == "--puzzle" || a == "--puzzle-file") {
+        match puzzles_from_args(&args) {
+            Ok(puzzles) => puzzle_mode(&puzzles),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(2);
+            }
+        }
+        return;
+    }
+    let (engine, params, limits) = engine_from_args()
+        .and_then(|engine| Ok((engine, eval_params_from_args()?, limits_from_args()?)))
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(2);
+        });
+    let ponder = env::args().any(|a| a == "--ponder");
+    let mut ai = Ai::Ready(engine);
     let mut game = Gomoku::new();
+    game.set_eval_params(params);
     println!("Welcome to Gomoku!");
     println!("Do you want to move first? (y/n)");
     let mut input = String::new();
@@ -26,21 +354,12 @@ fn main() {
         ai_color,
         if ai_color == Cell::Black { 'X' } else { 'O' }
     );
-    println!("Enter moves as 'row col' (e.g., '7 7').");
+    println!("Enter moves as 'row col' (e.g., '7 7'), 'hint' for a suggested move, 'threats'");
+    println!("to list the threats on the board, or 'draw' to offer a draw.");
 
     if !human_first {
-        println!("AI ({:?}) is thinking...", ai_color);
-        let (row, col) = game.ai_move();
-        println!("AI moves to ({}, {})", row, col);
-        game.make_move(row, col).expect("AI made an invalid move");
-        if let Some(winner) = game.check_winner() {
-            game.print_board();
-            println!("AI wins ({:?})!", winner);
-            return;
-        }
-        if game.is_board_full() {
-            game.print_board();
-            println!("Game is a draw!");
+        ai = ai_turn(ai, &mut game, &limits, ponder);
+        if game_over(&game, human_color) {
             return;
         }
         game.switch_player();
@@ -52,6 +371,26 @@ fn main() {
             println!("Your turn ({:?}). Enter row and column (0-{}):", human_color, BOARD_SIZE - 1);
             let mut input = String::new();
             io::stdin().read_line(&mut input).expect("Failed to read input");
+            match input.trim().to_ascii_lowercase().as_str() {
+                "threats" => {
+                    print_threats(&game);
+                    continue;
+                }
+                "hint" => {
+                    println!("Hint: {}", game.hint(HINT_TIME_MS));
+                    continue;
+                }
+                "draw" => {
+                    if game.accepts_draw(ai_color) {
+                        game.print_board();
+                        println!("AI accepts the draw. Game is a draw!");
+                        break;
+                    }
+                    println!("AI declines the draw.");
+                    continue;
+                }
+                _ => {}
+            }
             let coords: Vec<usize> = input
                 .split_whitespace()
                 .filter_map(|s| s.parse().ok())
@@ -66,26 +405,20 @@ fn main() {
                 continue;
             }
         } else {
-            println!("AI ({:?}) is thinking...", ai_color);
-            let (row, col) = game.ai_move();
-            println!("AI moves to ({}, {})", row, col);
-            game.make_move(row, col).expect("AI made an invalid move");
+            ai = ai_turn(ai, &mut game, &limits, ponder);
         }
 
-        if let Some(winner) = game.check_winner() {
-            game.print_board();
-            match winner {
-                w if w == human_color => println!("You win ({:?})!", human_color),
-                w if w == ai_color => println!("AI wins ({:?})!", ai_color),
-                _ => unreachable!(),
-            }
-            break;
-        }
-        if game.is_board_full() {
-            game.print_board();
-            println!("Game is a draw!");
+        if game_over(&game, human_color) {
             break;
         }
         game.switch_player();
     }
+
+    // A game ended by the human's move or a draw may leave the AI
+    // pondering; dropping it stops the search before the analysis runs.
+    drop(ai);
+    if let Err(err) = analyze_finished_game(&game) {
+        eprintln!("{}", err);
+        process::exit(2);
+    }
 }
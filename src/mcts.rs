@@ -0,0 +1,553 @@
+//! Monte Carlo tree search (UCT) engine.
+//!
+//! Instead of a hand-written evaluation, MCTS estimates moves by playing
+//! many games to the end from the current position and counting how often
+//! each side wins. The tree grows towards the moves that win most often
+//! while the UCT formula keeps trying less explored alternatives.
+
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::clock::Stopwatch;
+use crate::rng::Rng;
+use crate::vct::offset;
+use crate::{BOARD_SIZE, Cell, DIRECTIONS, Gomoku, MoveEval, PvLine, SearchResult, WIN_LENGTH};
+
+/// Cells this far from existing stones are considered by the tree.
+const TREE_RADIUS: usize = 2;
+
+/// Settings for [`Gomoku::mcts_move`].
+#[derive(Clone, Copy, Debug)]
+pub struct MctsConfig {
+    /// Maximum number of playouts.
+    pub playouts: usize,
+    /// Optional time budget in milliseconds. The search stops at whichever
+    /// of the two limits is reached first.
+    pub time_limit_ms: Option<u64>,
+    /// UCT exploration constant; larger values favor less visited moves.
+    pub exploration: f64,
+    /// Whether playouts follow the threats around the last stones instead
+    /// of playing purely at random: they complete and block fives, make
+    /// open fours, answer open threes and make fours.
+    pub heuristic_rollouts: bool,
+    /// Seed for the random number generator.
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            playouts: 5000,
+            time_limit_ms: None,
+            exploration: std::f64::consts::SQRT_2,
+            heuristic_rollouts: true,
+            seed: 0,
+        }
+    }
+}
+
+struct Node {
+    mv: (usize, usize),
+    /// The player who played `mv`.
+    player: Cell,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<(usize, usize)>,
+    visits: u32,
+    /// Sum of playout results from the point of view of `player`.
+    reward: f64,
+}
+
+fn color_index(color: Cell) -> usize {
+    match color {
+        Cell::White => 1,
+        _ => 0,
+    }
+}
+
+/// A position being played out, with the bookkeeping needed to choose
+/// playout moves quickly.
+#[derive(Clone)]
+struct Playout {
+    game: Gomoku,
+    /// Cells where each color would complete five, indexed by color.
+    fives: [Vec<(usize, usize)>; 2],
+    /// Empty cells adjacent to at least one stone.
+    frontier: Vec<(usize, usize)>,
+    /// Index of each cell in `frontier`, or `usize::MAX` if absent.
+    slot: [[usize; BOARD_SIZE]; BOARD_SIZE],
+    /// The last stone placed by each color, indexed by color.
+    last: [Option<(usize, usize)>; 2],
+    winner: Option<Cell>,
+}
+
+impl Playout {
+    fn new(game: &Gomoku) -> Self {
+        let mut playout = Playout {
+            game: game.clone(),
+            fives: [Vec::new(), Vec::new()],
+            frontier: Vec::new(),
+            slot: [[usize::MAX; BOARD_SIZE]; BOARD_SIZE],
+            last: [None, None],
+            winner: game.check_winner(),
+        };
+        for (row, col) in game.candidate_moves(1) {
+            playout.add_frontier(row, col);
+            for color in [Cell::Black, Cell::White] {
+                if game.completes_five(row, col, color) {
+                    playout.fives[color_index(color)].push((row, col));
+                }
+            }
+        }
+        playout
+    }
+
+    fn add_frontier(&mut self, row: usize, col: usize) {
+        if self.slot[row][col] == usize::MAX {
+            self.slot[row][col] = self.frontier.len();
+            self.frontier.push((row, col));
+        }
+    }
+
+    fn remove_frontier(&mut self, row: usize, col: usize) {
+        let index = self.slot[row][col];
+        if index == usize::MAX {
+            return;
+        }
+        self.frontier.swap_remove(index);
+        if let Some(&(r, c)) = self.frontier.get(index) {
+            self.slot[r][c] = index;
+        }
+        self.slot[row][col] = usize::MAX;
+    }
+
+    /// Place a stone for the player to move and pass the turn.
+    fn play(&mut self, row: usize, col: usize) {
+        let player = self.game.current_player;
+        if self.game.completes_five(row, col, player) {
+            self.winner = Some(player);
+        }
+        self.game.set_cell(row, col, player);
+        self.last[color_index(player)] = Some((row, col));
+        self.remove_frontier(row, col);
+        for fives in &mut self.fives {
+            fives.retain(|&p| p != (row, col));
+        }
+
+        for r in row.saturating_sub(1)..=(row + 1).min(BOARD_SIZE - 1) {
+            for c in col.saturating_sub(1)..=(col + 1).min(BOARD_SIZE - 1) {
+                if self.game.board[r][c] == Cell::Empty {
+                    self.add_frontier(r, c);
+                }
+            }
+        }
+
+        // New five points can only appear on the lines through the stone.
+        let reach = WIN_LENGTH as i32 - 1;
+        for &(dr, dc) in DIRECTIONS.iter() {
+            for step in -reach..=reach {
+                let r = row as i32 + dr * step;
+                let c = col as i32 + dc * step;
+                if r < 0 || r >= BOARD_SIZE as i32 || c < 0 || c >= BOARD_SIZE as i32 {
+                    continue;
+                }
+                let (r, c) = (r as usize, c as usize);
+                let fives = &self.fives[color_index(player)];
+                if self.game.board[r][c] == Cell::Empty
+                    && !fives.contains(&(r, c))
+                    && self.game.completes_five_along(r, c, (dr, dc), player)
+                {
+                    self.fives[color_index(player)].push((r, c));
+                }
+            }
+        }
+        self.game.switch_player();
+    }
+
+    /// Moves worth adding to the tree: a winning move or a forced block if
+    /// there is one, otherwise every cell near the stones.
+    fn tree_moves(&self) -> Vec<(usize, usize)> {
+        let player = self.game.current_player;
+        if let Some(&win) = self.fives[color_index(player)].first() {
+            return vec![win];
+        }
+        if let Some(&block) = self.fives[color_index(player.opponent())].first() {
+            return vec![block];
+        }
+        self.game.candidate_moves(TREE_RADIUS)
+    }
+
+    /// The most urgent threat move on the lines through the last stones:
+    /// an open four of the player to move, then a block of the opponent's
+    /// open three, then a four. Checking only those lines keeps playouts
+    /// fast, as new threats appear next to the stone just placed.
+    fn threat_move(&self) -> Option<(usize, usize)> {
+        let player = self.game.current_player;
+        let mut best: Option<(u8, (usize, usize))> = None;
+        for color in [player, player.opponent()] {
+            let Some(stone) = self.last[color_index(color)] else {
+                continue;
+            };
+            for &dir in DIRECTIONS.iter() {
+                for step in -(WIN_LENGTH as i32 - 1)..WIN_LENGTH as i32 {
+                    let Some((r, c)) = offset(stone, dir, step) else {
+                        continue;
+                    };
+                    if self.game.board[r][c] != Cell::Empty {
+                        continue;
+                    }
+                    let points = self.game.five_points_along(r, c, dir, color);
+                    let priority = match (color == player, points) {
+                        (true, 2..) => 3,
+                        (false, 2..) => 2,
+                        (true, 1) => 1,
+                        _ => continue,
+                    };
+                    if best.is_none_or(|(p, _)| priority > p) {
+                        best = Some((priority, (r, c)));
+                    }
+                }
+            }
+        }
+        best.map(|(_, mv)| mv)
+    }
+
+    /// Play moves until the game ends and return the winner. Heuristic
+    /// playouts follow fives and threats and play at random otherwise.
+    fn rollout(&mut self, rng: &mut Rng, heuristic: bool) -> Option<Cell> {
+        while self.winner.is_none() {
+            let player = self.game.current_player;
+            let own = &self.fives[color_index(player)];
+            let theirs = &self.fives[color_index(player.opponent())];
+            let threat = if heuristic && own.is_empty() && theirs.is_empty() {
+                self.threat_move()
+            } else {
+                None
+            };
+            let (row, col) = if heuristic && !own.is_empty() {
+                own[0]
+            } else if heuristic && !theirs.is_empty() {
+                theirs[0]
+            } else if let Some(mv) = threat {
+                mv
+            } else if self.frontier.is_empty() {
+                return None;
+            } else {
+                self.frontier[rng.below(self.frontier.len())]
+            };
+            self.play(row, col);
+        }
+        self.winner
+    }
+}
+
+impl Gomoku {
+    /// Choose a move for the current player with Monte Carlo tree search.
+    ///
+    /// The most visited move after the playouts is returned. Per-move win
+    /// rates (in percent) are recorded for [`Gomoku::evaluation_at`].
+    pub fn mcts_move(&mut self, config: &MctsConfig) -> (usize, usize) {
+        let result = self.mcts_search(config, None);
+        self.last_evaluations = result.evaluations;
+        result.best_move
+    }
+
+    /// Run the tree search without touching the recorded evaluations.
+    /// Setting `stop` ends the playouts early.
+    pub(crate) fn mcts_search(&self, config: &MctsConfig, stop: Option<&AtomicBool>) -> SearchResult {
+        let root = Playout::new(self);
+        let root_moves = root.tree_moves();
+        if root_moves.len() <= 1 {
+            let Some(&mv) = root_moves.first() else {
+                return SearchResult {
+                    best_move: (BOARD_SIZE / 2, BOARD_SIZE / 2),
+                    ..SearchResult::default()
+                };
+            };
+            return SearchResult {
+                best_move: mv,
+                depth: 1,
+                pv: vec![mv],
+                lines: vec![PvLine { score: 0, pv: vec![mv] }],
+                ..SearchResult::default()
+            };
+        }
+
+        let first_move = root_moves[0];
+        let mut rng = Rng::new(config.seed);
+        let mut nodes = vec![Node {
+            mv: (BOARD_SIZE / 2, BOARD_SIZE / 2),
+            player: self.current_player.opponent(),
+            parent: None,
+            children: Vec::new(),
+            untried: root_moves,
+            visits: 0,
+            reward: 0.0,
+        }];
+        let clock = Stopwatch::start();
+
+        // The first playout always runs, so that the root has a child to
+        // play even when the time is up or the search was stopped at once.
+        let mut playouts = 0;
+        for _ in 0..config.playouts.max(1) {
+            if playouts > 0
+                && (config
+                    .time_limit_ms
+                    .is_some_and(|limit| clock.elapsed_ms() >= limit)
+                    || stop.is_some_and(|stop| stop.load(Ordering::Relaxed)))
+            {
+                break;
+            }
+            let mut state = root.clone();
+            let mut node = 0;
+
+            // Selection: descend through fully expanded nodes by UCT.
+            while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+                let log_visits = (nodes[node].visits as f64).ln();
+                let mut best = nodes[node].children[0];
+                let mut best_score = f64::MIN;
+                for &child in &nodes[node].children {
+                    let n = &nodes[child];
+                    let visits = n.visits as f64;
+                    let score =
+                        n.reward / visits + config.exploration * (log_visits / visits).sqrt();
+                    if score > best_score {
+                        best_score = score;
+                        best = child;
+                    }
+                }
+                node = best;
+                let (row, col) = nodes[node].mv;
+                state.play(row, col);
+            }
+
+            // Expansion: add one untried move unless the game is over.
+            if state.winner.is_none() && !nodes[node].untried.is_empty() {
+                let untried = &mut nodes[node].untried;
+                let (row, col) = untried.swap_remove(rng.below(untried.len()));
+                let player = state.game.current_player;
+                state.play(row, col);
+                let untried = if state.winner.is_some() {
+                    Vec::new()
+                } else {
+                    state.tree_moves()
+                };
+                nodes.push(Node {
+                    mv: (row, col),
+                    player,
+                    parent: Some(node),
+                    children: Vec::new(),
+                    untried,
+                    visits: 0,
+                    reward: 0.0,
+                });
+                let child = nodes.len() - 1;
+                nodes[node].children.push(child);
+                node = child;
+            }
+
+            // Simulation and backpropagation.
+            playouts += 1;
+            let winner = state.rollout(&mut rng, config.heuristic_rollouts);
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let n = &mut nodes[index];
+                n.visits += 1;
+                n.reward += match winner {
+                    Some(w) if w == n.player => 1.0,
+                    None => 0.5,
+                    _ => 0.0,
+                };
+                current = n.parent;
+            }
+        }
+
+        let mut evaluations = Vec::with_capacity(nodes[0].children.len());
+        let mut best = 0;
+        for (i, &child) in nodes[0].children.iter().enumerate() {
+            let n = &nodes[child];
+            evaluations.push(MoveEval {
+                row: n.mv.0,
+                col: n.mv.1,
+                value: (100.0 * n.reward / n.visits as f64).round() as i32,
+            });
+            if n.visits > nodes[nodes[0].children[best]].visits {
+                best = i;
+            }
+        }
+        let best = evaluations.get(best).copied().unwrap_or(MoveEval {
+            row: first_move.0,
+            col: first_move.1,
+            value: 0,
+        });
+        // Lines follow the first most visited child, as the best move does
+        // at the root.
+        let most_visited = |node: usize| {
+            nodes[node].children.iter().copied().reduce(|a, b| {
+                if nodes[b].visits > nodes[a].visits { b } else { a }
+            })
+        };
+        let line_from = |child: usize| {
+            let mut pv = vec![nodes[child].mv];
+            let mut node = child;
+            while let Some(next) = most_visited(node) {
+                pv.push(nodes[next].mv);
+                node = next;
+            }
+            pv
+        };
+        let mut ranked: Vec<(usize, MoveEval)> =
+            nodes[0].children.iter().copied().zip(evaluations.iter().copied()).collect();
+        ranked.sort_by_key(|&(child, _)| Reverse(nodes[child].visits));
+        let lines: Vec<PvLine> = ranked
+            .into_iter()
+            .map(|(child, eval)| PvLine {
+                score: eval.value,
+                pv: line_from(child),
+            })
+            .collect();
+        let pv = lines
+            .first()
+            .map_or_else(|| vec![(best.row, best.col)], |line| line.pv.clone());
+        SearchResult {
+            best_move: (best.row, best.col),
+            score: best.value,
+            evaluations,
+            depth: pv.len() as u32,
+            pv,
+            nodes: playouts,
+            elapsed_ms: clock.elapsed_ms(),
+            lines,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(playouts: usize) -> MctsConfig {
+        MctsConfig {
+            playouts,
+            ..MctsConfig::default()
+        }
+    }
+
+    #[test]
+    /// An available five is played without searching.
+    fn mcts_completes_five() {
+        let mut game = Gomoku::new();
+        for col in 3..7 {
+            game.set_cell(7, col, Cell::Black);
+        }
+        game.set_cell(7, 2, Cell::White);
+        for row in 0..3 {
+            game.set_cell(row, 0, Cell::White);
+        }
+        assert_eq!(game.mcts_move(&config(100)), (7, 7));
+    }
+
+    #[test]
+    /// A forced move is returned with itself as the line.
+    fn mcts_forced_move_has_line() {
+        let mut game = Gomoku::new();
+        for col in 3..7 {
+            game.set_cell(7, col, Cell::White);
+        }
+        game.set_cell(7, 2, Cell::Black);
+        let result = game.mcts_search(&config(100), None);
+        assert_eq!(result.best_move, (7, 7));
+        assert_eq!(result.pv, vec![(7, 7)]);
+        assert_eq!(result.lines[0].pv, vec![(7, 7)]);
+    }
+
+    #[test]
+    /// Heuristic playouts turn their own three into an open four before
+    /// blocking the opponent's, block an open three otherwise, and make a
+    /// four when nothing more urgent is on the board.
+    fn playouts_follow_threats() {
+        let mut game = Gomoku::new();
+        for col in 5..8 {
+            game.push_move(7, col).unwrap();
+            game.push_move(9, col).unwrap();
+        }
+        let mut playout = Playout::new(&game);
+        playout.last = [Some((7, 7)), Some((9, 7))];
+        assert!([(7, 4), (7, 8)].contains(&playout.threat_move().unwrap()));
+
+        playout.last = [Some((7, 7)), None];
+        playout.game.current_player = Cell::White;
+        assert!([(7, 4), (7, 8)].contains(&playout.threat_move().unwrap()));
+
+        let mut game = Gomoku::new();
+        game.set_cell(7, 4, Cell::White);
+        for col in 5..8 {
+            game.set_cell(7, col, Cell::Black);
+        }
+        let mut playout = Playout::new(&game);
+        playout.last = [Some((7, 7)), None];
+        assert!([(7, 8), (7, 9)].contains(&playout.threat_move().unwrap()));
+    }
+
+    #[test]
+    /// The opponent's four must be blocked.
+    fn mcts_blocks_four() {
+        let mut game = Gomoku::new();
+        for col in 3..7 {
+            game.set_cell(7, col, Cell::White);
+        }
+        game.set_cell(7, 2, Cell::Black);
+        game.set_cell(8, 8, Cell::Black);
+        assert_eq!(game.mcts_move(&config(100)), (7, 7));
+    }
+
+    #[test]
+    /// The same seed reproduces the same search.
+    fn mcts_is_reproducible() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 7, Cell::Black);
+        game.set_cell(7, 8, Cell::White);
+        game.set_cell(8, 7, Cell::Black);
+
+        let mut a = game.clone();
+        let mut b = game.clone();
+        let config = MctsConfig {
+            seed: 7,
+            ..config(300)
+        };
+        let (row, col) = a.mcts_move(&config);
+        assert_eq!(b.mcts_move(&config), (row, col));
+        assert_eq!(a.evaluation_at(row, col), b.evaluation_at(row, col));
+    }
+
+    #[test]
+    /// A time budget ends the search even with a huge playout count.
+    fn mcts_respects_time_limit() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 7, Cell::Black);
+        let config = MctsConfig {
+            playouts: usize::MAX,
+            time_limit_ms: Some(50),
+            ..MctsConfig::default()
+        };
+        let clock = Stopwatch::start();
+        let (row, col) = game.mcts_move(&config);
+        assert!(clock.elapsed_ms() < 2000);
+        assert_eq!(game.board[row][col], Cell::Empty);
+    }
+
+    #[test]
+    /// With no time at all one playout still runs and picks an empty cell,
+    /// not the occupied centre.
+    fn mcts_without_time_plays_legal_move() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        let config = MctsConfig {
+            time_limit_ms: Some(0),
+            ..MctsConfig::default()
+        };
+        let result = game.mcts_search(&config, None);
+        assert_eq!(result.nodes, 1);
+        assert_eq!(game.board[result.best_move.0][result.best_move.1], Cell::Empty);
+    }
+}
@@ -0,0 +1,130 @@
+//! Evaluation weights that can be changed without recompiling.
+//!
+//! Parameters are stored as simple `name = value` lines, one per shape:
+//!
+//! ```text
+//! # Weights for the shape evaluation.
+//! [weights]
+//! five = 100000
+//! open_four = 10000
+//! open_three = 300
+//! ```
+//!
+//! Names are those of [`Shape::name`]. Shapes that are not listed keep
+//! their default weight. Blank lines, `#` comments and `[section]` headers
+//! are ignored.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{SHAPE_COUNT, Shape};
+
+/// Weights of the shapes counted by the evaluation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalParams {
+    /// Weight of each shape, indexed by [`Shape::index`].
+    pub weights: [i32; SHAPE_COUNT],
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            weights: Shape::ALL.map(Shape::weight),
+        }
+    }
+}
+
+impl EvalParams {
+    /// Weight of one occurrence of `shape`.
+    pub fn weight(&self, shape: Shape) -> i32 {
+        self.weights[shape.index()]
+    }
+
+    /// Change the weight of `shape`.
+    pub fn set_weight(&mut self, shape: Shape, weight: i32) {
+        self.weights[shape.index()] = weight;
+    }
+
+    /// Parse parameters in the format described in the module docs,
+    /// starting from the defaults.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut params = EvalParams::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `name = value`", number + 1))?;
+            let (name, value) = (name.trim(), value.trim());
+            let shape = Shape::from_name(name)
+                .ok_or_else(|| format!("line {}: unknown shape '{}'", number + 1, name))?;
+            let weight = value
+                .parse()
+                .map_err(|_| format!("line {}: invalid weight '{}'", number + 1, value))?;
+            params.set_weight(shape, weight);
+        }
+        Ok(params)
+    }
+
+    /// Read parameters from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        EvalParams::parse(&text).map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+
+    /// Write the parameters to a file that [`EvalParams::load`] reads back.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+/// Formats the parameters as a complete parameter file.
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[weights]")?;
+        for shape in Shape::ALL {
+            writeln!(f, "{} = {}", shape.name(), self.weight(shape))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Listed weights are replaced and the others keep their defaults.
+    fn parse_overrides_defaults() {
+        let params = EvalParams::parse(
+            "# tuned\n[weights]\nopen_three = 400  # was 300\n\n  split_two=-3\n",
+        )
+        .unwrap();
+        assert_eq!(params.weight(Shape::OpenThree), 400);
+        assert_eq!(params.weight(Shape::SplitTwo), -3);
+        assert_eq!(params.weight(Shape::OpenFour), Shape::OpenFour.weight());
+    }
+
+    #[test]
+    /// Malformed lines are reported with their line number.
+    fn parse_rejects_bad_lines() {
+        assert_eq!(
+            EvalParams::parse("five = 1\nsix = 2").unwrap_err(),
+            "line 2: unknown shape 'six'"
+        );
+        assert!(EvalParams::parse("four = lots").is_err());
+        assert!(EvalParams::parse("four 1000").is_err());
+    }
+
+    #[test]
+    /// Formatted parameters parse back to the same values.
+    fn display_round_trips() {
+        let mut params = EvalParams::default();
+        params.set_weight(Shape::BrokenFour, 1234);
+        assert_eq!(EvalParams::parse(&params.to_string()).unwrap(), params);
+    }
+}
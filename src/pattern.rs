@@ -0,0 +1,382 @@
+//! Line-pattern classification for the evaluation function.
+//!
+//! Every line of the board (rows, columns and both diagonals) is split, for
+//! each player, into segments bounded by the opponent's stones and the
+//! board edges. A segment is described completely by its length and the
+//! positions of the player's stones in it, so the shapes it contains are
+//! looked up in a table that is computed once for every possible segment.
+//!
+//! # Scoring spec
+//!
+//! Shapes are matched strongest first, and every stone counts towards at
+//! most one shape per line, so a run of three is one open three rather
+//! than three overlapping runs. In the patterns below `X` is a stone of the
+//! player and `_` an empty cell; cells outside the segment count as
+//! blocked.
+//!
+//! | Shape         | Patterns                          | Weight |
+//! |---------------|-----------------------------------|-------:|
+//! | `Five`        | `XXXXX`                           | 100000 |
+//! | `OpenFour`    | `_XXXX_`                          |  10000 |
+//! | `Four`        | `XXXX_`, `_XXXX`                  |   1000 |
+//! | `BrokenFour`  | `X_XXX`, `XXX_X`, `XX_XX`         |   1000 |
+//! | `OpenThree`   | `__XXX_`, `_XXX__`                |    300 |
+//! | `BrokenThree` | `_X_XX_`, `_XX_X_`                |    250 |
+//! | `ClosedThree` | any other three stones in 5 cells |     50 |
+//! | `OpenTwo`     | `__XX__`, `_XX___`, `___XX_`      |     30 |
+//! | `SplitTwo`    | `_X_X__`, `__X_X_`, `_X__X_`      |     20 |
+//! | `ClosedTwo`   | any other two stones in 5 cells   |      5 |
+//!
+//! Segments shorter than five cells can never hold a five and score
+//! nothing. The evaluation of a position is the weighted shape count of
+//! one player minus that of the other. The weights above are the defaults
+//! of [`EvalParams`](crate::EvalParams).
+
+use std::sync::OnceLock;
+
+use crate::bitboard::{self, Bitboard, LINE_IDS};
+use crate::{BOARD_SIZE, Cell, EvalParams, WIN_LENGTH, WIN_SCORE};
+
+/// Number of distinct [`Shape`]s.
+pub const SHAPE_COUNT: usize = 10;
+
+/// Shapes a player's stones can form along a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    /// Five or more in a row.
+    Five,
+    /// Four with both ends open; cannot be stopped.
+    OpenFour,
+    /// Four in a row with one end blocked.
+    Four,
+    /// Four stones in five cells with a gap, e.g. `XX_XX`.
+    BrokenFour,
+    /// Three in a row that can become an open four.
+    OpenThree,
+    /// Three stones with a gap that can become an open four.
+    BrokenThree,
+    /// Three stones that can only become a closed four.
+    ClosedThree,
+    /// Two in a row that can become an open three.
+    OpenTwo,
+    /// Two stones with a gap that can become a broken three.
+    SplitTwo,
+    /// Two stones that can only become a closed three.
+    ClosedTwo,
+}
+
+impl Shape {
+    /// Every shape, strongest first.
+    pub const ALL: [Shape; SHAPE_COUNT] = [
+        Shape::Five,
+        Shape::OpenFour,
+        Shape::Four,
+        Shape::BrokenFour,
+        Shape::OpenThree,
+        Shape::BrokenThree,
+        Shape::ClosedThree,
+        Shape::OpenTwo,
+        Shape::SplitTwo,
+        Shape::ClosedTwo,
+    ];
+
+    /// Position of the shape in [`Shape::ALL`].
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Name of the shape in parameter files, e.g. `open_three`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Five => "five",
+            Shape::OpenFour => "open_four",
+            Shape::Four => "four",
+            Shape::BrokenFour => "broken_four",
+            Shape::OpenThree => "open_three",
+            Shape::BrokenThree => "broken_three",
+            Shape::ClosedThree => "closed_three",
+            Shape::OpenTwo => "open_two",
+            Shape::SplitTwo => "split_two",
+            Shape::ClosedTwo => "closed_two",
+        }
+    }
+
+    /// The shape with the given [`Shape::name`].
+    pub fn from_name(name: &str) -> Option<Shape> {
+        Shape::ALL.into_iter().find(|shape| shape.name() == name)
+    }
+
+    /// Default evaluation weight of one occurrence of the shape, as listed
+    /// in the scoring spec. [`EvalParams`](crate::EvalParams) can override
+    /// it.
+    pub fn weight(self) -> i32 {
+        match self {
+            Shape::Five => WIN_SCORE,
+            Shape::OpenFour => 10000,
+            Shape::Four => 1000,
+            Shape::BrokenFour => 1000,
+            Shape::OpenThree => 300,
+            Shape::BrokenThree => 250,
+            Shape::ClosedThree => 50,
+            Shape::OpenTwo => 30,
+            Shape::SplitTwo => 20,
+            Shape::ClosedTwo => 5,
+        }
+    }
+}
+
+/// Shapes found by explicit pattern, in the order they are matched.
+const PATTERNS: [(Shape, &[&[u8]]); 8] = [
+    (Shape::Five, &[b"XXXXX"]),
+    (Shape::OpenFour, &[b"_XXXX_"]),
+    (Shape::Four, &[b"XXXX_", b"_XXXX"]),
+    (Shape::BrokenFour, &[b"X_XXX", b"XXX_X", b"XX_XX"]),
+    (Shape::OpenThree, &[b"__XXX_", b"_XXX__"]),
+    (Shape::BrokenThree, &[b"_X_XX_", b"_XX_X_"]),
+    (Shape::OpenTwo, &[b"__XX__", b"_XX___", b"___XX_"]),
+    (Shape::SplitTwo, &[b"_X_X__", b"__X_X_", b"_X__X_"]),
+];
+
+/// Number of occurrences of each shape, indexed by [`Shape::index`].
+pub type ShapeCounts = [u32; SHAPE_COUNT];
+
+const EMPTY: u8 = 0;
+const STONE: u8 = 1;
+/// A stone already counted towards a stronger shape.
+const USED: u8 = 2;
+
+/// Mark every occurrence of `pattern` among the unused stones of `cells`.
+fn match_pattern(cells: &mut [u8], pattern: &[u8]) -> u8 {
+    let mut found = 0;
+    if pattern.len() > cells.len() {
+        return 0;
+    }
+    for start in 0..=cells.len() - pattern.len() {
+        let fits = pattern.iter().enumerate().all(|(i, &p)| {
+            let cell = cells[start + i];
+            if p == b'X' { cell == STONE } else { cell == EMPTY }
+        });
+        if fits {
+            found += 1;
+            for (i, &p) in pattern.iter().enumerate() {
+                if p == b'X' {
+                    cells[start + i] = USED;
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Count the five-cell windows holding exactly `stones` unused stones and
+/// otherwise empty cells, marking their stones as used.
+fn match_closed(cells: &mut [u8], stones: usize) -> u8 {
+    let mut found = 0;
+    if cells.len() < WIN_LENGTH {
+        return 0;
+    }
+    for start in 0..=cells.len() - WIN_LENGTH {
+        let window = &cells[start..start + WIN_LENGTH];
+        if window.contains(&USED) || window.iter().filter(|&&c| c == STONE).count() != stones {
+            continue;
+        }
+        found += 1;
+        for cell in &mut cells[start..start + WIN_LENGTH] {
+            if *cell == STONE {
+                *cell = USED;
+            }
+        }
+    }
+    found
+}
+
+/// Shapes in a segment of `len` cells whose stones are the set bits of
+/// `stones`.
+fn classify(len: usize, stones: u32) -> [u8; SHAPE_COUNT] {
+    let mut counts = [0; SHAPE_COUNT];
+    if len < WIN_LENGTH {
+        return counts;
+    }
+    let mut cells: Vec<u8> = (0..len)
+        .map(|i| if stones >> i & 1 == 1 { STONE } else { EMPTY })
+        .collect();
+    for &(shape, patterns) in &PATTERNS[..6] {
+        for pattern in patterns {
+            counts[shape.index()] += match_pattern(&mut cells, pattern);
+        }
+    }
+    counts[Shape::ClosedThree.index()] += match_closed(&mut cells, 3);
+    for &(shape, patterns) in &PATTERNS[6..] {
+        for pattern in patterns {
+            counts[shape.index()] += match_pattern(&mut cells, pattern);
+        }
+    }
+    counts[Shape::ClosedTwo.index()] += match_closed(&mut cells, 2);
+    counts
+}
+
+/// Shape counts of every segment, indexed by `(1 << len) - 1 + stones`.
+fn segment_table() -> &'static [[u8; SHAPE_COUNT]] {
+    static TABLE: OnceLock<Vec<[u8; SHAPE_COUNT]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Vec::with_capacity((1 << (BOARD_SIZE + 1)) - 1);
+        for len in 0..=BOARD_SIZE {
+            for stones in 0..1u32 << len {
+                table.push(classify(len, stones));
+            }
+        }
+        table
+    })
+}
+
+/// Add the shapes of one segment to `counts`.
+pub(crate) fn add_segment(counts: &mut ShapeCounts, len: usize, stones: u32) {
+    let shapes = &segment_table()[(1 << len) - 1 + stones as usize];
+    for (count, &n) in counts.iter_mut().zip(shapes.iter()) {
+        *count += n as u32;
+    }
+}
+
+/// Add the shapes formed by the `own` stones of a line of `len` cells to
+/// `counts`, splitting the line into segments at the `opponent` stones.
+pub(crate) fn add_bits(counts: &mut ShapeCounts, own: u16, opponent: u16, len: usize) {
+    // A blocker just past the end closes the last segment.
+    let blockers = opponent as u32 | 1 << len;
+    let mut start = 0;
+    while start <= len {
+        let end = start + (blockers >> start).trailing_zeros() as usize;
+        let seg_len = end - start;
+        add_segment(counts, seg_len, (own as u32 >> start) & ((1 << seg_len) - 1));
+        start = end + 1;
+    }
+}
+
+/// Add the shapes `player` forms along every line of `bits` to `counts`.
+pub(crate) fn add_board(counts: &mut ShapeCounts, bits: &Bitboard, player: Cell) {
+    for line in bitboard::playable_lines() {
+        add_bits(
+            counts,
+            bits.line(player, line),
+            bits.line(player.opponent(), line),
+            bitboard::line_len(line),
+        );
+    }
+}
+
+fn color_index(color: Cell) -> usize {
+    match color {
+        Cell::White => 1,
+        _ => 0,
+    }
+}
+
+/// Shape counts of every line kept up to date as stones come and go, so
+/// the evaluation never has to rescan the board.
+#[derive(Clone)]
+pub(crate) struct LineCache {
+    /// Shapes of each line id, for Black and White.
+    shapes: [[[u8; SHAPE_COUNT]; 2]; LINE_IDS],
+    /// Sum of `shapes` over all lines, for Black and White.
+    totals: [ShapeCounts; 2],
+}
+
+impl LineCache {
+    /// The cache of an empty board, on which no line holds a shape.
+    pub(crate) fn new() -> Self {
+        LineCache {
+            shapes: [[[0; SHAPE_COUNT]; 2]; LINE_IDS],
+            totals: [[0; SHAPE_COUNT]; 2],
+        }
+    }
+
+    /// Update the lines through `(row, col)` after that cell changed.
+    pub(crate) fn update(&mut self, bits: &Bitboard, row: usize, col: usize) {
+        for direction in 0..4 {
+            let (line, _) = bitboard::locate(direction, row, col);
+            let len = bitboard::line_len(line);
+            if len >= WIN_LENGTH {
+                self.refresh(bits, line, len);
+            }
+        }
+    }
+
+    fn refresh(&mut self, bits: &Bitboard, line: usize, len: usize) {
+        for player in [Cell::Black, Cell::White] {
+            let mut counts = [0; SHAPE_COUNT];
+            let own = bits.line(player, line);
+            add_bits(&mut counts, own, bits.line(player.opponent(), line), len);
+            let color = color_index(player);
+            let total = &mut self.totals[color];
+            for ((total, old), &new) in total
+                .iter_mut()
+                .zip(self.shapes[line][color].iter_mut())
+                .zip(counts.iter())
+            {
+                *total = *total - *old as u32 + new;
+                *old = new as u8;
+            }
+        }
+    }
+
+    /// Shape counts of `player` over the whole board.
+    pub(crate) fn totals(&self, player: Cell) -> &ShapeCounts {
+        &self.totals[color_index(player)]
+    }
+}
+
+/// Sum of shape counts weighted by `params`.
+pub(crate) fn score(counts: &ShapeCounts, params: &EvalParams) -> i32 {
+    counts
+        .iter()
+        .zip(params.weights.iter())
+        .map(|(&count, &weight)| count as i32 * weight)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Classify a pattern written with `X` and `_` as a whole segment.
+    fn shapes(segment: &str) -> [u8; SHAPE_COUNT] {
+        let stones = segment
+            .bytes()
+            .enumerate()
+            .filter(|&(_, b)| b == b'X')
+            .fold(0, |acc, (i, _)| acc | 1 << i);
+        classify(segment.len(), stones)
+    }
+
+    fn only(shape: Shape) -> [u8; SHAPE_COUNT] {
+        let mut counts = [0; SHAPE_COUNT];
+        counts[shape.index()] = 1;
+        counts
+    }
+
+    #[test]
+    /// Each documented pattern is recognised as exactly one shape.
+    fn standard_shapes_are_classified() {
+        assert_eq!(shapes("XXXXX"), only(Shape::Five));
+        assert_eq!(shapes("_XXXX_"), only(Shape::OpenFour));
+        assert_eq!(shapes("XXXX__"), only(Shape::Four));
+        assert_eq!(shapes("_XX_XX_"), only(Shape::BrokenFour));
+        assert_eq!(shapes("__XXX__"), only(Shape::OpenThree));
+        assert_eq!(shapes("_X_XX_"), only(Shape::BrokenThree));
+        assert_eq!(shapes("XXX___"), only(Shape::ClosedThree));
+        assert_eq!(shapes("_XXX_"), only(Shape::ClosedThree));
+        assert_eq!(shapes("___XX___"), only(Shape::OpenTwo));
+        assert_eq!(shapes("_X__X_"), only(Shape::SplitTwo));
+        assert_eq!(shapes("XX___"), only(Shape::ClosedTwo));
+    }
+
+    #[test]
+    /// Stones are not counted twice and short segments are dead.
+    fn shapes_do_not_overlap() {
+        assert_eq!(shapes("XXXX"), [0; SHAPE_COUNT]);
+        assert_eq!(shapes("_XXXXX_"), only(Shape::Five));
+
+        let mut two_threes = [0; SHAPE_COUNT];
+        two_threes[Shape::OpenThree.index()] = 2;
+        assert_eq!(shapes("__XXX____XXX__"), two_threes);
+    }
+}
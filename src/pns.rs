@@ -0,0 +1,592 @@
+//! Depth-first proof-number search (df-pn) for solving positions.
+//!
+//! Unlike minimax, which estimates how good a position is, proof-number
+//! search tries to prove that the side to move can force five in a row, or
+//! that it cannot. Every position carries a proof number (how many leaves
+//! still need to be shown winning) and a disproof number (how many need to
+//! be shown not winning), and the search always expands the part of the
+//! tree that is cheapest to settle. Results are kept in a transposition
+//! table that can be saved to disk and loaded again to resume work.
+//!
+//! Moves can be restricted to a rectangular [`Region`] of the board, which
+//! turns the search into a solver for smaller boards.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{BOARD_SIZE, Cell, DIRECTIONS, Gomoku, WIN_LENGTH, zobrist};
+
+const INF: u32 = u32::MAX;
+
+/// File signature of a saved proof table.
+const TABLE_MAGIC: &[u8; 4] = b"GPNS";
+const TABLE_VERSION: u32 = 1;
+/// Size of one saved entry: key, proof number and disproof number.
+const ENTRY_BYTES: usize = 16;
+
+/// Key mixed into every table entry when White is the attacker.
+const WHITE_ATTACKS: u64 = 0x2545_F491_4F6C_DD1D;
+
+/// Rectangular part of the board on which moves may be played.
+///
+/// Stones outside the region still count towards lines running into it,
+/// but no new stones are placed there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub top: usize,
+    pub left: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Region {
+    /// The whole board.
+    pub fn full() -> Self {
+        Region {
+            top: 0,
+            left: 0,
+            height: BOARD_SIZE,
+            width: BOARD_SIZE,
+        }
+    }
+
+    /// Whether the cell lies inside the region.
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row >= self.top
+            && row < self.top + self.height
+            && col >= self.left
+            && col < self.left + self.width
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let bottom = (self.top + self.height).min(BOARD_SIZE);
+        let right = (self.left + self.width).min(BOARD_SIZE);
+        let left = self.left;
+        (self.top..bottom).flat_map(move |row| (left..right).map(move |col| (row, col)))
+    }
+
+    /// Key distinguishing table entries computed for different regions.
+    fn key(&self) -> u64 {
+        let packed = (self.top | self.left << 8 | self.height << 16 | self.width << 24) as u64;
+        packed.wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(29)
+    }
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region::full()
+    }
+}
+
+/// Resource limits for a proof-number search.
+#[derive(Clone, Copy, Debug)]
+pub struct PnsLimits {
+    /// Maximum number of positions expanded per call to
+    /// [`ProofSearch::solve`].
+    pub max_nodes: usize,
+    /// Maximum number of transposition table entries kept in memory.
+    pub max_entries: usize,
+}
+
+impl Default for PnsLimits {
+    fn default() -> Self {
+        PnsLimits {
+            max_nodes: 1_000_000,
+            max_entries: 1_000_000,
+        }
+    }
+}
+
+/// Game-theoretic value of a position for the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PnsOutcome {
+    /// The side to move can force five in a row.
+    Win,
+    /// The side to move cannot force five in a row. Drawn positions are
+    /// reported as a loss because the search only proves wins.
+    Loss,
+    /// The limits were reached before the position was solved.
+    Unknown,
+}
+
+/// Outcome of [`ProofSearch::solve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PnsResult {
+    pub outcome: PnsOutcome,
+    /// Number of distinct positions in the proof (or disproof) tree, or
+    /// zero when the outcome is unknown.
+    pub proof_size: usize,
+    /// Number of positions expanded by this call.
+    pub nodes: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Bounds {
+    pn: u32,
+    dn: u32,
+}
+
+const UNSEEN: Bounds = Bounds { pn: 1, dn: 1 };
+const PROVEN: Bounds = Bounds { pn: 0, dn: INF };
+const DISPROVEN: Bounds = Bounds { pn: INF, dn: 0 };
+
+/// Proof-number solver with a persistent transposition table.
+///
+/// The same `ProofSearch` can be used for several positions; work done for
+/// earlier calls is reused through the shared table.
+pub struct ProofSearch {
+    table: HashMap<u64, Bounds>,
+    limits: PnsLimits,
+}
+
+impl ProofSearch {
+    /// Create a solver with an empty table.
+    pub fn new(limits: PnsLimits) -> Self {
+        ProofSearch {
+            table: HashMap::new(),
+            limits,
+        }
+    }
+
+    /// Number of positions currently stored in the table.
+    pub fn table_len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Determine whether the player to move in `game` can force a win with
+    /// moves restricted to `region`.
+    pub fn solve(&mut self, game: &Gomoku, region: Region) -> PnsResult {
+        let attacker = game.current_player;
+        let salt = region.key()
+            ^ if attacker == Cell::White {
+                WHITE_ATTACKS
+            } else {
+                0
+            };
+        let mut solver = Solver {
+            table: &mut self.table,
+            limits: self.limits,
+            game: game.clone(),
+            region,
+            attacker,
+            hash: game.hash() ^ salt,
+            nodes: 0,
+            stopped: false,
+        };
+
+        if let Some(winner) = game.check_winner() {
+            let outcome = if winner == attacker {
+                PnsOutcome::Win
+            } else {
+                PnsOutcome::Loss
+            };
+            return PnsResult {
+                outcome,
+                proof_size: 1,
+                nodes: 0,
+            };
+        }
+
+        solver.mid(INF, INF);
+        let root = solver.lookup(solver.hash);
+        let outcome = if root.pn == 0 {
+            PnsOutcome::Win
+        } else if root.dn == 0 {
+            PnsOutcome::Loss
+        } else {
+            PnsOutcome::Unknown
+        };
+        let proof_size = match outcome {
+            PnsOutcome::Unknown => 0,
+            _ => solver.proof_size(outcome == PnsOutcome::Win, &mut HashSet::new()),
+        };
+        PnsResult {
+            outcome,
+            proof_size,
+            nodes: solver.nodes,
+        }
+    }
+
+    /// Write the transposition table to `path` in a compact binary format.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(16 + self.table.len() * ENTRY_BYTES);
+        bytes.extend_from_slice(TABLE_MAGIC);
+        bytes.extend_from_slice(&TABLE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.table.len() as u64).to_le_bytes());
+        for (&key, bounds) in &self.table {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&bounds.pn.to_le_bytes());
+            bytes.extend_from_slice(&bounds.dn.to_le_bytes());
+        }
+        fs::write(path, bytes)
+    }
+
+    /// Create a solver whose table is restored from a file written by
+    /// [`ProofSearch::save`].
+    pub fn load<P: AsRef<Path>>(path: P, limits: PnsLimits) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        if bytes.len() < 16 || &bytes[..4] != TABLE_MAGIC {
+            return Err(invalid("not a proof table"));
+        }
+        if u32::from_le_bytes(bytes[4..8].try_into().unwrap()) != TABLE_VERSION {
+            return Err(invalid("unsupported proof table version"));
+        }
+        let count = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let entries = &bytes[16..];
+        if entries.len() != count.saturating_mul(ENTRY_BYTES) {
+            return Err(invalid("truncated proof table"));
+        }
+        let table = entries
+            .chunks_exact(ENTRY_BYTES)
+            .map(|e| {
+                let key = u64::from_le_bytes(e[..8].try_into().unwrap());
+                let pn = u32::from_le_bytes(e[8..12].try_into().unwrap());
+                let dn = u32::from_le_bytes(e[12..16].try_into().unwrap());
+                (key, Bounds { pn, dn })
+            })
+            .collect();
+        Ok(ProofSearch { table, limits })
+    }
+}
+
+/// Children of a position, or its value if the game is decided.
+enum Expansion {
+    /// The attacker has (`true`) or has not (`false`) won.
+    Terminal(bool),
+    Moves(Vec<(usize, usize)>),
+}
+
+struct Solver<'a> {
+    table: &'a mut HashMap<u64, Bounds>,
+    limits: PnsLimits,
+    game: Gomoku,
+    region: Region,
+    attacker: Cell,
+    hash: u64,
+    nodes: usize,
+    stopped: bool,
+}
+
+impl Solver<'_> {
+    fn lookup(&self, key: u64) -> Bounds {
+        self.table.get(&key).copied().unwrap_or(UNSEEN)
+    }
+
+    fn store(&mut self, key: u64, bounds: Bounds) {
+        if self.table.len() >= self.limits.max_entries && !self.table.contains_key(&key) {
+            self.stopped = true;
+            return;
+        }
+        self.table.insert(key, bounds);
+    }
+
+    fn child_key(&self, row: usize, col: usize) -> u64 {
+        self.hash ^ zobrist::stone(self.game.current_player, row, col) ^ zobrist::WHITE_TO_MOVE
+    }
+
+    fn play(&mut self, row: usize, col: usize) {
+        self.hash = self.child_key(row, col);
+        self.game.set_cell(row, col, self.game.current_player);
+        self.game.switch_player();
+    }
+
+    fn undo(&mut self, row: usize, col: usize) {
+        self.game.switch_player();
+        self.game.set_cell(row, col, Cell::Empty);
+        self.hash = self.child_key(row, col);
+    }
+
+    /// Empty cells of the region where `player` would complete five.
+    fn five_points(&self, player: Cell) -> Vec<(usize, usize)> {
+        self.region
+            .cells()
+            .filter(|&(r, c)| {
+                self.game.board[r][c] == Cell::Empty && self.game.completes_five(r, c, player)
+            })
+            .collect()
+    }
+
+    /// Whether some line of five cells could still be filled by the
+    /// attacker using its own stones and empty cells of the region.
+    fn attacker_can_win(&self) -> bool {
+        let usable = |r: i32, c: i32| {
+            if r < 0 || r >= BOARD_SIZE as i32 || c < 0 || c >= BOARD_SIZE as i32 {
+                return false;
+            }
+            let (r, c) = (r as usize, c as usize);
+            let cell = self.game.board[r][c];
+            cell == self.attacker || (cell == Cell::Empty && self.region.contains(r, c))
+        };
+        self.region.cells().any(|(row, col)| {
+            DIRECTIONS.iter().any(|&(dr, dc)| {
+                (0..WIN_LENGTH as i32)
+                    .all(|step| usable(row as i32 + dr * step, col as i32 + dc * step))
+            })
+        })
+    }
+
+    fn expand(&self) -> Expansion {
+        let to_move = self.game.current_player;
+        if !self.five_points(to_move).is_empty() {
+            return Expansion::Terminal(to_move == self.attacker);
+        }
+        let threats = self.five_points(to_move.opponent());
+        if threats.len() > 1 {
+            return Expansion::Terminal(to_move != self.attacker);
+        }
+        if !self.attacker_can_win() {
+            return Expansion::Terminal(false);
+        }
+        if !threats.is_empty() {
+            return Expansion::Moves(threats);
+        }
+        let moves: Vec<(usize, usize)> = self
+            .region
+            .cells()
+            .filter(|&(r, c)| self.game.board[r][c] == Cell::Empty)
+            .collect();
+        if moves.is_empty() {
+            Expansion::Terminal(false)
+        } else {
+            Expansion::Moves(moves)
+        }
+    }
+
+    /// Multiple iterative deepening: expand the current position until its
+    /// proof number reaches `th_pn` or its disproof number reaches `th_dn`.
+    fn mid(&mut self, th_pn: u32, th_dn: u32) {
+        if self.nodes >= self.limits.max_nodes {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return;
+        }
+        self.nodes += 1;
+
+        let moves = match self.expand() {
+            Expansion::Terminal(win) => {
+                self.store(self.hash, if win { PROVEN } else { DISPROVEN });
+                return;
+            }
+            Expansion::Moves(moves) => moves,
+        };
+        let or_node = self.game.current_player == self.attacker;
+
+        loop {
+            // At an OR node the attacker needs one winning child; at an AND
+            // node every defender reply must be covered.
+            let mut pn = if or_node { INF } else { 0 };
+            let mut dn = if or_node { 0 } else { INF };
+            let mut best = 0;
+            let mut best_value = INF;
+            let mut second = INF;
+            let mut best_bounds = UNSEEN;
+            for (i, &(r, c)) in moves.iter().enumerate() {
+                let child = self.lookup(self.child_key(r, c));
+                let value = if or_node {
+                    pn = pn.min(child.pn);
+                    dn = dn.saturating_add(child.dn);
+                    child.pn
+                } else {
+                    pn = pn.saturating_add(child.pn);
+                    dn = dn.min(child.dn);
+                    child.dn
+                };
+                if value < best_value {
+                    second = best_value;
+                    best_value = value;
+                    best = i;
+                    best_bounds = child;
+                } else if value < second {
+                    second = value;
+                }
+            }
+            self.store(self.hash, Bounds { pn, dn });
+            if pn >= th_pn || dn >= th_dn || self.stopped {
+                return;
+            }
+
+            let (child_pn, child_dn) = if or_node {
+                (
+                    th_pn.min(second.saturating_add(1)),
+                    (th_dn - dn).saturating_add(best_bounds.dn),
+                )
+            } else {
+                (
+                    (th_pn - pn).saturating_add(best_bounds.pn),
+                    th_dn.min(second.saturating_add(1)),
+                )
+            };
+            let (r, c) = moves[best];
+            self.play(r, c);
+            self.mid(child_pn, child_dn);
+            self.undo(r, c);
+        }
+    }
+
+    /// Count the distinct positions of the proof tree below the current
+    /// position, following one solved move at nodes where a single move
+    /// suffices and every move elsewhere.
+    fn proof_size(&mut self, win: bool, seen: &mut HashSet<u64>) -> usize {
+        if !seen.insert(self.hash) {
+            return 0;
+        }
+        let moves = match self.expand() {
+            Expansion::Terminal(_) => return 1,
+            Expansion::Moves(moves) => moves,
+        };
+        let or_node = self.game.current_player == self.attacker;
+        let every_move = or_node != win;
+        let mut size = 1;
+        for (r, c) in moves {
+            let child = self.lookup(self.child_key(r, c));
+            let solved = if win { child.pn == 0 } else { child.dn == 0 };
+            if !solved {
+                continue;
+            }
+            self.play(r, c);
+            size += self.proof_size(win, seen);
+            self.undo(r, c);
+            if !every_move {
+                break;
+            }
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VctLimits;
+
+    fn row_region(width: usize) -> Region {
+        Region {
+            top: 0,
+            left: 0,
+            height: 1,
+            width,
+        }
+    }
+
+    #[test]
+    /// No line of five fits on a 4x4 board, so nobody can win there.
+    fn four_by_four_board_is_not_a_win() {
+        let game = Gomoku::new();
+        let region = Region {
+            top: 5,
+            left: 5,
+            height: 4,
+            width: 4,
+        };
+        let result = ProofSearch::new(PnsLimits::default()).solve(&game, region);
+        assert_eq!(result.outcome, PnsOutcome::Loss);
+        assert_eq!(result.proof_size, 1);
+    }
+
+    #[test]
+    /// On a single row the second player can always block every five.
+    fn single_row_is_not_a_win() {
+        let game = Gomoku::new();
+        let result = ProofSearch::new(PnsLimits::default()).solve(&game, row_region(7));
+        assert_eq!(result.outcome, PnsOutcome::Loss);
+        assert!(result.proof_size > 1);
+    }
+
+    #[test]
+    /// An open three on an otherwise empty row becomes an open four.
+    fn open_three_on_a_row_is_a_win() {
+        let mut game = Gomoku::new();
+        for col in 2..5 {
+            game.set_cell(0, col, Cell::Black);
+        }
+        let result = ProofSearch::new(PnsLimits::default()).solve(&game, row_region(7));
+        assert_eq!(result.outcome, PnsOutcome::Win);
+        assert_eq!(result.proof_size, 2);
+    }
+
+    #[test]
+    /// The solver proves a double four found anywhere inside a region.
+    fn double_four_is_proven_in_region() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 2, Cell::White);
+        game.set_cell(2, 6, Cell::White);
+        for col in 3..6 {
+            game.set_cell(7, col, Cell::Black);
+        }
+        for row in 3..6 {
+            game.set_cell(row, 6, Cell::Black);
+        }
+        let region = Region {
+            top: 2,
+            left: 2,
+            height: 7,
+            width: 7,
+        };
+        let result = ProofSearch::new(PnsLimits::default()).solve(&game, region);
+        assert_eq!(result.outcome, PnsOutcome::Win);
+    }
+
+    #[test]
+    /// Two open twos meeting at (7, 7) make a three-three there, which
+    /// wins. The solver agrees with the threat search, and once Black
+    /// has played it White cannot hold on to a win of its own.
+    fn three_three_matches_threat_search() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 5, Cell::Black);
+        game.set_cell(7, 6, Cell::Black);
+        game.set_cell(5, 7, Cell::Black);
+        game.set_cell(6, 7, Cell::Black);
+        let region = Region {
+            top: 4,
+            left: 4,
+            height: 6,
+            width: 6,
+        };
+        let vct = game.vct(Cell::Black, &VctLimits::default());
+        assert_eq!(vct.line.as_ref().map(|line| line[0]), Some((7, 7)));
+
+        let mut search = ProofSearch::new(PnsLimits::default());
+        let result = search.solve(&game, region);
+        assert_eq!(result.outcome, PnsOutcome::Win);
+        assert!(result.proof_size > 2);
+
+        game.push_move(7, 7).unwrap();
+        let result = search.solve(&game, region);
+        assert_eq!(result.outcome, PnsOutcome::Loss);
+    }
+
+    #[test]
+    /// A saved table answers the same question without searching again.
+    fn table_round_trips_through_disk() {
+        let game = Gomoku::new();
+        let mut search = ProofSearch::new(PnsLimits::default());
+        let first = search.solve(&game, row_region(7));
+
+        let path = std::env::temp_dir().join(format!("gomoku-pns-{}.bin", std::process::id()));
+        search.save(&path).unwrap();
+        let mut restored = ProofSearch::load(&path, PnsLimits::default()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.table_len(), search.table_len());
+        let second = restored.solve(&game, row_region(7));
+        assert_eq!(second.outcome, first.outcome);
+        assert_eq!(second.proof_size, first.proof_size);
+        assert_eq!(second.nodes, 1);
+    }
+
+    #[test]
+    /// Running out of table space leaves the position unsolved.
+    fn memory_limit_stops_search() {
+        let limits = PnsLimits {
+            max_nodes: 1_000_000,
+            max_entries: 8,
+        };
+        let result = ProofSearch::new(limits).solve(&Gomoku::new(), row_region(7));
+        assert_eq!(result.outcome, PnsOutcome::Unknown);
+        assert_eq!(result.proof_size, 0);
+    }
+}
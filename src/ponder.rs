@@ -0,0 +1,203 @@
+//! Searching in the background, and pondering on the opponent's time.
+//!
+//! After the AI has moved, its principal variation predicts the
+//! opponent's reply. [`Ponder`] starts searching the position after that
+//! reply while the opponent thinks. If the opponent plays the predicted
+//! move the running search simply continues, having had a head start;
+//! otherwise it is stopped and thrown away.
+//!
+//! Searches run on their own thread, which the WebAssembly build cannot
+//! start; the browser runs its searches in a Web Worker instead.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crate::{Engine, Gomoku, SearchControl, SearchLimits, SearchResult};
+
+/// An engine that can be moved to a search thread.
+pub type SendEngine = Box<dyn Engine + Send>;
+
+/// A search running on its own thread. The engine is handed back with the
+/// result when the search ends. Dropping it stops the search and waits
+/// for the thread, so that an abandoned search does not keep a core busy.
+pub struct BackgroundSearch {
+    stop: Arc<AtomicBool>,
+    /// Taken when the search is waited for.
+    handle: Option<JoinHandle<(SendEngine, SearchResult)>>,
+}
+
+impl BackgroundSearch {
+    /// Start searching `game` with `engine` within `limits`.
+    pub fn start(mut engine: SendEngine, game: Gomoku, limits: SearchLimits) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut control = SearchControl {
+                stop: Some(&flag),
+                ..SearchControl::default()
+            };
+            let result = engine.search(&game, &limits, &mut control);
+            (engine, result)
+        });
+        BackgroundSearch {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Whether the search has ended.
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Wait for the search to end within its limits.
+    pub fn wait(mut self) -> (SendEngine, SearchResult) {
+        let handle = self.handle.take().expect("the search is waited for once");
+        handle.join().expect("background search panicked")
+    }
+
+    /// Stop the search and return the best result found so far.
+    pub fn stop(self) -> (SendEngine, SearchResult) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait()
+    }
+}
+
+impl Drop for BackgroundSearch {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            // A panic on the search thread has nobody left to report to.
+            let _ = handle.join();
+        }
+    }
+}
+
+/// A background search of the position after the opponent's predicted
+/// reply.
+pub struct Ponder {
+    /// The reply the search assumes.
+    pub predicted: (usize, usize),
+    search: BackgroundSearch,
+}
+
+impl Ponder {
+    /// Start pondering in `game`, where the opponent is to move after the
+    /// AI played the first move of `result`. Hands the engine back when
+    /// the principal variation predicts no legal reply.
+    pub fn start(
+        engine: SendEngine,
+        game: &Gomoku,
+        result: &SearchResult,
+        limits: SearchLimits,
+    ) -> Result<Ponder, SendEngine> {
+        let Some(&predicted) = result.pv.get(1) else {
+            return Err(engine);
+        };
+        let mut position = game.clone();
+        if game.check_winner().is_some() || position.push_move(predicted.0, predicted.1).is_err() {
+            return Err(engine);
+        }
+        Ok(Ponder {
+            predicted,
+            search: BackgroundSearch::start(engine, position, limits),
+        })
+    }
+
+    /// The opponent played `reply`. On a ponder hit the search is finished
+    /// and its result returned; otherwise it is stopped and only the
+    /// engine is returned.
+    pub fn finish(self, reply: (usize, usize)) -> (SendEngine, Option<SearchResult>) {
+        if reply == self.predicted {
+            let (engine, result) = self.search.wait();
+            (engine, Some(result))
+        } else {
+            (self.search.stop().0, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Stopwatch;
+    use crate::{MinimaxEngine, SearchLimits};
+
+    fn opening() -> Gomoku {
+        let mut game = Gomoku::new();
+        for (row, col) in [(7, 7), (7, 8), (8, 8)] {
+            game.push_move(row, col).unwrap();
+        }
+        game
+    }
+
+    fn depth(plies: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(plies),
+            ..SearchLimits::default()
+        }
+    }
+
+    #[test]
+    /// A background search finds the same move as a direct search, and
+    /// stopping a long one returns quickly.
+    fn background_search_matches_direct_search() {
+        let game = opening();
+        let expected = MinimaxEngine::single_threaded().choose_move(&game, &depth(2));
+        let search = BackgroundSearch::start(
+            Box::new(MinimaxEngine::single_threaded()),
+            game.clone(),
+            depth(2),
+        );
+        let (_, result) = search.wait();
+        assert_eq!((result.best_move, result.score), (expected.best_move, expected.score));
+
+        let clock = Stopwatch::start();
+        let search = BackgroundSearch::start(Box::new(MinimaxEngine::default()), game, depth(20));
+        thread::sleep(std::time::Duration::from_millis(50));
+        let (_, result) = search.stop();
+        assert!(clock.elapsed_ms() < 10_000);
+        assert!(result.depth >= 1);
+    }
+
+    #[test]
+    /// Dropping a ponder stops its search, as when the game ends before
+    /// the opponent's reply.
+    fn dropped_ponder_stops_search() {
+        let mut game = opening();
+        let mut engine: SendEngine = Box::new(MinimaxEngine::single_threaded());
+        let result = engine.choose_move(&game, &depth(2));
+        game.push_move(result.best_move.0, result.best_move.1).unwrap();
+        let ponder = Ponder::start(engine, &game, &result, depth(20)).ok().unwrap();
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!ponder.search.is_finished());
+
+        let clock = Stopwatch::start();
+        drop(ponder);
+        assert!(clock.elapsed_ms() < 10_000);
+    }
+
+    #[test]
+    /// A ponder hit returns the search of the predicted position and a
+    /// miss returns nothing.
+    fn ponder_hit_reuses_search() {
+        let mut game = opening();
+        let mut engine: SendEngine = Box::new(MinimaxEngine::single_threaded());
+        let result = engine.choose_move(&game, &depth(2));
+        game.push_move(result.best_move.0, result.best_move.1).unwrap();
+
+        let ponder = Ponder::start(engine, &game, &result, depth(2)).ok().unwrap();
+        let predicted = ponder.predicted;
+        assert_eq!(predicted, result.pv[1]);
+        let (engine, hit) = ponder.finish(predicted);
+        game.push_move(predicted.0, predicted.1).unwrap();
+        let expected = MinimaxEngine::single_threaded().choose_move(&game, &depth(2));
+        assert_eq!(hit.unwrap().best_move, expected.best_move);
+        game.pop_move();
+
+        let ponder = Ponder::start(engine, &game, &result, depth(2)).ok().unwrap();
+        let miss = if predicted == (0, 0) { (0, 1) } else { (0, 0) };
+        assert!(ponder.finish(miss).1.is_none());
+    }
+}
@@ -0,0 +1,641 @@
+//! Tactics puzzles: find the winning move or the forced win.
+//!
+//! Puzzles are stored as text, one `[puzzle]` section per puzzle with
+//! `key = value` lines:
+//!
+//! ```text
+//! # Black wins with two fours on one move.
+//! [puzzle]
+//! name = Crossing fours
+//! goal = vcf
+//! to_move = black
+//! black = 7,4 7,5 7,6 4,7 5,7 6,7
+//! white = 7,3 3,7 8,8 9,4 4,10 12,12
+//! solution = 7,7 7,8 8,7
+//! rating = 1
+//! ```
+//!
+//! The goal is one of the [`PuzzleGoal`] names. Stones and moves are
+//! `row,col` pairs separated by spaces. Each `solution` is a winning line
+//! alternating the solver's moves and the defender's replies and ending
+//! with five; a puzzle lists at least one. The `rating`, a difficulty from
+//! 1 to 5, may be left out. Blank lines and `#` comments are ignored.
+//!
+//! Moves are checked by [`PuzzleAttempt`]. A move of a listed solution is
+//! answered as listed; any other move is accepted when the threat-space
+//! search proves that it still wins, so correct alternatives count too.
+//!
+//! [`Puzzle::mine`] turns positions of played games into puzzles; the
+//! `puzzles` tool mines them from self-play games.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::{BOARD_SIZE, Cell, Gomoku, VctLimits};
+
+/// The puzzles that ship with the crate, in the format of the module docs.
+const BUILTIN: &str = include_str!("../puzzles/tactics.txt");
+
+/// Limits of the searches that solve puzzles and check moves that are not
+/// part of a listed solution.
+const SOLVE_LIMITS: VctLimits = VctLimits {
+    max_depth: 10,
+    max_nodes: 100_000,
+};
+
+/// What the solver of a puzzle has to find.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PuzzleGoal {
+    /// Five in a row with a single move.
+    Five,
+    /// A victory by continuous fours.
+    Vcf,
+    /// A victory by continuous threats, fours and threes.
+    Vct,
+}
+
+impl PuzzleGoal {
+    /// Every goal, easiest first.
+    pub const ALL: [PuzzleGoal; 3] = [PuzzleGoal::Five, PuzzleGoal::Vcf, PuzzleGoal::Vct];
+
+    /// Name of the goal in puzzle files: `five`, `vcf` or `vct`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PuzzleGoal::Five => "five",
+            PuzzleGoal::Vcf => "vcf",
+            PuzzleGoal::Vct => "vct",
+        }
+    }
+
+    /// The goal with the given [`PuzzleGoal::name`].
+    pub fn from_name(name: &str) -> Option<PuzzleGoal> {
+        PuzzleGoal::ALL.into_iter().find(|g| g.name() == name)
+    }
+
+    /// The goal as a task, e.g. `win with continuous fours`.
+    pub fn description(self) -> &'static str {
+        match self {
+            PuzzleGoal::Five => "win with one move",
+            PuzzleGoal::Vcf => "win with continuous fours",
+            PuzzleGoal::Vct => "win with continuous threats",
+        }
+    }
+}
+
+/// A position in which the side to move has a forced win.
+#[derive(Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub goal: PuzzleGoal,
+    /// The starting position, with the solver to move and no move history.
+    pub position: Gomoku,
+    /// Winning lines alternating the solver's and the defender's moves,
+    /// each ending with five.
+    pub solutions: Vec<Vec<(usize, usize)>>,
+    /// Difficulty from 1 to 5, if the puzzle has been rated.
+    pub rating: Option<u32>,
+}
+
+impl Puzzle {
+    /// The side that has to find the win.
+    pub fn player(&self) -> Cell {
+        self.position.current_player()
+    }
+
+    /// Search the position for a win that reaches the goal, independently
+    /// of the listed solutions. Returns the line found, or `None` if the
+    /// search gives up.
+    pub fn solve(&self) -> Option<Vec<(usize, usize)>> {
+        let player = self.player();
+        match self.goal {
+            PuzzleGoal::Five => self.position.five_points(player).first().map(|&mv| vec![mv]),
+            PuzzleGoal::Vcf => self.position.vcf(player, &SOLVE_LIMITS).line,
+            PuzzleGoal::Vct => self.position.vct(player, &SOLVE_LIMITS).line,
+        }
+    }
+
+    /// A puzzle for the side to move in `game` if it has a forced win
+    /// that only one first move starts: by continuous fours if there is
+    /// one, otherwise by continuous threats. Positions in which either
+    /// side can already make five are skipped, and so are positions for
+    /// which `limits` do not suffice to rule out other first moves.
+    ///
+    /// The puzzle is rated by the length of the win and the effort of
+    /// finding it: one point for each of the solver's moves before the
+    /// five, one more for a win that needs threes, and one more for each
+    /// tenfold increase of the nodes searched beyond 100, up to 5.
+    pub fn mine(game: &Gomoku, name: &str, limits: &VctLimits) -> Option<Puzzle> {
+        let player = game.current_player();
+        let mut position = Gomoku::new();
+        for (row, col) in game.bits.stones() {
+            position.set_cell(row, col, game.board[row][col]);
+        }
+        position.current_player = player;
+        if game.check_winner().is_some()
+            || !position.five_points(player).is_empty()
+            || !position.five_points(player.opponent()).is_empty()
+        {
+            return None;
+        }
+
+        let vcf = position.vcf(player, limits);
+        let (goal, result) = match vcf.line {
+            Some(_) => (PuzzleGoal::Vcf, vcf),
+            None => (PuzzleGoal::Vct, position.vct(player, limits)),
+        };
+        let line = result.line?;
+        let threes = goal == PuzzleGoal::Vct;
+        for mv in position.candidate_moves(2) {
+            if mv == line[0] {
+                continue;
+            }
+            let other = position.threat_search_from(player, mv, limits, threes);
+            if other.line.is_some() || other.nodes >= limits.max_nodes {
+                return None;
+            }
+        }
+
+        let moves = line.len().div_ceil(2) as u32 - 1;
+        let mut effort = 0;
+        let mut nodes = result.nodes / 100;
+        while nodes >= 10 {
+            effort += 1;
+            nodes /= 10;
+        }
+        let rating = (moves + threes as u32 + effort).clamp(1, 5);
+        Some(Puzzle {
+            name: name.to_string(),
+            goal,
+            position,
+            solutions: vec![line],
+            rating: Some(rating),
+        })
+    }
+
+    /// Parse a collection of puzzles in the format of the module docs.
+    pub fn parse_all(text: &str) -> Result<Vec<Puzzle>, String> {
+        let mut puzzles = Vec::new();
+        let mut current: Option<PuzzleFields> = None;
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "[puzzle]" {
+                if let Some(fields) = current.take() {
+                    puzzles.push(fields.build(puzzles.len() + 1)?);
+                }
+                current = Some(PuzzleFields::new(number));
+                continue;
+            }
+            let fields = current
+                .as_mut()
+                .ok_or_else(|| format!("line {}: expected `[puzzle]`", number))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", number))?;
+            fields
+                .set(key.trim(), value.trim())
+                .map_err(|msg| format!("line {}: {}", number, msg))?;
+        }
+        if let Some(fields) = current {
+            puzzles.push(fields.build(puzzles.len() + 1)?);
+        }
+        Ok(puzzles)
+    }
+
+    /// Read a collection of puzzles from a file.
+    pub fn load_all<P: AsRef<Path>>(path: P) -> io::Result<Vec<Puzzle>> {
+        let text = fs::read_to_string(path)?;
+        Puzzle::parse_all(&text).map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+
+    /// Write puzzles to a file that [`Puzzle::load_all`] reads back.
+    pub fn save_all<P: AsRef<Path>>(path: P, puzzles: &[Puzzle]) -> io::Result<()> {
+        let sections: Vec<String> = puzzles.iter().map(Puzzle::to_string).collect();
+        fs::write(path, sections.join("\n"))
+    }
+
+    /// The puzzles that ship with the crate.
+    pub fn builtin() -> &'static [Puzzle] {
+        static PUZZLES: OnceLock<Vec<Puzzle>> = OnceLock::new();
+        PUZZLES.get_or_init(|| Puzzle::parse_all(BUILTIN).expect("the built-in puzzles are valid"))
+    }
+}
+
+/// Format `row,col` pairs separated by spaces.
+fn format_moves(moves: &[(usize, usize)]) -> String {
+    let moves: Vec<String> = moves.iter().map(|(row, col)| format!("{},{}", row, col)).collect();
+    moves.join(" ")
+}
+
+/// Parse `row,col` pairs separated by spaces.
+fn parse_moves(text: &str) -> Result<Vec<(usize, usize)>, String> {
+    text.split_whitespace()
+        .map(|pair| {
+            let (row, col) = pair.split_once(',').unwrap_or((pair, ""));
+            match (row.parse(), col.parse()) {
+                (Ok(row), Ok(col)) if row < BOARD_SIZE && col < BOARD_SIZE => Ok((row, col)),
+                _ => Err(format!("invalid move '{}'", pair)),
+            }
+        })
+        .collect()
+}
+
+/// Formats the puzzle as a `[puzzle]` section of a puzzle file.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[puzzle]")?;
+        writeln!(f, "name = {}", self.name)?;
+        writeln!(f, "goal = {}", self.goal.name())?;
+        let to_move = if self.player() == Cell::White { "white" } else { "black" };
+        writeln!(f, "to_move = {}", to_move)?;
+        for (key, color) in [("black", Cell::Black), ("white", Cell::White)] {
+            let stones: Vec<_> = self
+                .position
+                .bits
+                .stones()
+                .into_iter()
+                .filter(|&(row, col)| self.position.board[row][col] == color)
+                .collect();
+            writeln!(f, "{} = {}", key, format_moves(&stones))?;
+        }
+        for solution in &self.solutions {
+            writeln!(f, "solution = {}", format_moves(solution))?;
+        }
+        if let Some(rating) = self.rating {
+            writeln!(f, "rating = {}", rating)?;
+        }
+        Ok(())
+    }
+}
+
+/// The keys of one `[puzzle]` section as they are read.
+struct PuzzleFields {
+    /// Line of the section header, for errors about the whole puzzle.
+    line: usize,
+    name: Option<String>,
+    goal: Option<PuzzleGoal>,
+    to_move: Option<Cell>,
+    black: Vec<(usize, usize)>,
+    white: Vec<(usize, usize)>,
+    solutions: Vec<Vec<(usize, usize)>>,
+    rating: Option<u32>,
+}
+
+impl PuzzleFields {
+    fn new(line: usize) -> Self {
+        PuzzleFields {
+            line,
+            name: None,
+            goal: None,
+            to_move: None,
+            black: Vec::new(),
+            white: Vec::new(),
+            solutions: Vec::new(),
+            rating: None,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "name" => self.name = Some(value.to_string()),
+            "goal" => {
+                let goal = PuzzleGoal::from_name(value)
+                    .ok_or_else(|| format!("unknown goal '{}'", value))?;
+                self.goal = Some(goal);
+            }
+            "to_move" => {
+                self.to_move = match value {
+                    "black" => Some(Cell::Black),
+                    "white" => Some(Cell::White),
+                    _ => return Err(format!("unknown player '{}'", value)),
+                }
+            }
+            "black" => self.black.extend(parse_moves(value)?),
+            "white" => self.white.extend(parse_moves(value)?),
+            "solution" => self.solutions.push(parse_moves(value)?),
+            "rating" => {
+                let rating = value
+                    .parse()
+                    .map_err(|_| format!("invalid rating '{}'", value))?;
+                self.rating = Some(rating);
+            }
+            _ => return Err(format!("unknown key '{}'", key)),
+        }
+        Ok(())
+    }
+
+    /// Check the section and set up the puzzle, named `Puzzle <index>`
+    /// unless it has a name.
+    fn build(self, index: usize) -> Result<Puzzle, String> {
+        let error = |msg: &str| format!("puzzle at line {}: {}", self.line, msg);
+        let goal = self.goal.ok_or_else(|| error("missing goal"))?;
+        let to_move = self.to_move.ok_or_else(|| error("missing to_move"))?;
+        if self.solutions.iter().all(|s| s.is_empty()) {
+            return Err(error("missing solution"));
+        }
+        let mut position = Gomoku::new();
+        for (stones, color) in [(&self.black, Cell::Black), (&self.white, Cell::White)] {
+            for &(row, col) in stones {
+                if position.board[row][col] != Cell::Empty {
+                    return Err(error(&format!("two stones on {},{}", row, col)));
+                }
+                position.set_cell(row, col, color);
+            }
+        }
+        position.current_player = to_move;
+        for solution in &self.solutions {
+            let mut game = position.clone();
+            for &(row, col) in solution {
+                if game.push_move(row, col).is_err() {
+                    return Err(error(&format!("solution plays on the stone at {},{}", row, col)));
+                }
+            }
+        }
+        Ok(Puzzle {
+            name: self.name.clone().unwrap_or_else(|| format!("Puzzle {}", index)),
+            goal,
+            position,
+            solutions: self.solutions,
+            rating: self.rating,
+        })
+    }
+}
+
+/// How a move of a [`PuzzleAttempt`] was judged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleVerdict {
+    /// The move makes five and solves the puzzle.
+    Solved,
+    /// The move keeps the win; the defender answered at `reply`.
+    Correct { reply: (usize, usize) },
+    /// The move lets the win slip. It is not played.
+    Wrong,
+}
+
+/// A puzzle being solved move by move, with the defender's replies played
+/// automatically.
+#[derive(Clone)]
+pub struct PuzzleAttempt {
+    puzzle: Puzzle,
+    game: Gomoku,
+    solved: bool,
+}
+
+impl PuzzleAttempt {
+    /// Start solving `puzzle`.
+    pub fn new(puzzle: Puzzle) -> Self {
+        let game = puzzle.position.clone();
+        PuzzleAttempt {
+            puzzle,
+            game,
+            solved: false,
+        }
+    }
+
+    /// The puzzle being solved.
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    /// The current position, whose history holds the moves played so far.
+    pub fn game(&self) -> &Gomoku {
+        &self.game
+    }
+
+    /// Whether the last move made five.
+    pub fn is_solved(&self) -> bool {
+        self.solved
+    }
+
+    /// Play the solver's move at `(row, col)` and, if it keeps the win,
+    /// the defender's reply.
+    ///
+    /// Returns an error if the move is off the board, on a stone, or the
+    /// puzzle is already solved.
+    pub fn play(&mut self, row: usize, col: usize) -> Result<PuzzleVerdict, &'static str> {
+        if self.solved {
+            return Err("Puzzle already solved");
+        }
+        let mut next = self.game.clone();
+        next.push_move(row, col)?;
+        let player = self.puzzle.player();
+        if self.game.completes_five(row, col, player) {
+            self.game = next;
+            self.solved = true;
+            return Ok(PuzzleVerdict::Solved);
+        }
+        let reply = match self.listed_line((row, col)) {
+            Some(line) => line.get(1).copied(),
+            None => self.proof((row, col)).and_then(|line| line.get(1).copied()),
+        };
+        let Some(reply) = reply else {
+            return Ok(PuzzleVerdict::Wrong);
+        };
+        next.push_move(reply.0, reply.1)?;
+        self.game = next;
+        Ok(PuzzleVerdict::Correct { reply })
+    }
+
+    /// A winning line from the current position: the rest of a listed
+    /// solution if the moves so far follow one, or else one found by the
+    /// search. Empty once solved.
+    pub fn solution(&self) -> Vec<(usize, usize)> {
+        if self.solved {
+            return Vec::new();
+        }
+        let played = self.game.moves();
+        if let Some(line) = self
+            .puzzle
+            .solutions
+            .iter()
+            .find(|line| line.len() > played.len() && line.starts_with(played))
+        {
+            return line[played.len()..].to_vec();
+        }
+        let player = self.puzzle.player();
+        let line = match self.puzzle.goal {
+            PuzzleGoal::Five => None,
+            PuzzleGoal::Vcf => self.game.vcf(player, &SOLVE_LIMITS).line,
+            PuzzleGoal::Vct => self.game.vct(player, &SOLVE_LIMITS).line,
+        };
+        line.unwrap_or_default()
+    }
+
+    /// The rest of a listed solution that continues the moves so far with
+    /// `mv`, starting with `mv`.
+    fn listed_line(&self, mv: (usize, usize)) -> Option<&[(usize, usize)]> {
+        let played = self.game.moves();
+        self.puzzle
+            .solutions
+            .iter()
+            .find(|line| line.starts_with(played) && line.get(played.len()) == Some(&mv))
+            .map(|line| &line[played.len()..])
+    }
+
+    /// A forced win reaching the goal that starts with `mv`, if the search
+    /// finds one.
+    fn proof(&self, mv: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let threes = match self.puzzle.goal {
+            PuzzleGoal::Five => return None,
+            PuzzleGoal::Vcf => false,
+            PuzzleGoal::Vct => true,
+        };
+        self.game
+            .threat_search_from(self.puzzle.player(), mv, &SOLVE_LIMITS, threes)
+            .line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CROSSING_FOURS: &str = "
+        # Black wins with two fours on one move.
+        [puzzle]
+        name = Crossing fours
+        goal = vcf
+        to_move = black
+        black = 7,4 7,5 7,6 4,7 5,7 6,7
+        white = 7,3 3,7 8,8 9,4 4,10 12,12
+        solution = 7,7 7,8 8,7
+        rating = 1
+    ";
+
+    const OPEN_THREE: &str = "
+        [puzzle]
+        goal = vcf
+        to_move = black
+        black = 7,5 7,6 7,7
+        white = 6,6 8,8 6,8
+        solution = 7,8 7,9 7,4
+    ";
+
+    #[test]
+    /// Puzzles parse from and format to the file format, and bad sections
+    /// are reported with their line.
+    fn puzzles_round_trip() {
+        let puzzles = Puzzle::parse_all(CROSSING_FOURS).unwrap();
+        assert_eq!(puzzles.len(), 1);
+        let puzzle = &puzzles[0];
+        assert_eq!((puzzle.name.as_str(), puzzle.goal), ("Crossing fours", PuzzleGoal::Vcf));
+        assert_eq!((puzzle.player(), puzzle.rating), (Cell::Black, Some(1)));
+        assert_eq!(puzzle.position.board[12][12], Cell::White);
+        assert!(puzzle.position.moves().is_empty());
+
+        let text = puzzle.to_string();
+        assert!(text.starts_with("[puzzle]\nname = Crossing fours\ngoal = vcf\n"));
+        let again = Puzzle::parse_all(&format!("{}\n{}", text, text)).unwrap();
+        assert_eq!(again.len(), 2);
+        assert_eq!(again[1].position.board, puzzle.position.board);
+        assert_eq!(again[1].solutions, puzzle.solutions);
+        assert_eq!(again[1].rating, puzzle.rating);
+
+        let error = |text: &str| Puzzle::parse_all(text).err();
+        assert_eq!(error("goal = vcf").as_deref(), Some("line 1: expected `[puzzle]`"));
+        assert_eq!(
+            error("[puzzle]\ngoal = vcf\nto_move = black").as_deref(),
+            Some("puzzle at line 1: missing solution")
+        );
+        assert!(error("[puzzle]\ngoal = vcf\nblack = 7,7 7,15").is_some());
+        assert!(error("[puzzle]\ngoal = vcf\nto_move = black\nblack = 7,7\nsolution = 7,7").is_some());
+    }
+
+    #[test]
+    /// Listed moves are answered as listed, alternatives that still win
+    /// are accepted and moves that let the win slip are refused.
+    fn attempts_accept_any_winning_move() {
+        let puzzle = Puzzle::parse_all(CROSSING_FOURS).unwrap().remove(0);
+        let mut attempt = PuzzleAttempt::new(puzzle.clone());
+        assert_eq!(attempt.play(0, 0), Ok(PuzzleVerdict::Wrong));
+        assert_eq!(attempt.play(7, 3), Err("Cell already occupied"));
+        assert_eq!(attempt.solution(), vec![(7, 7), (7, 8), (8, 7)]);
+        assert_eq!(attempt.play(7, 7), Ok(PuzzleVerdict::Correct { reply: (7, 8) }));
+        assert_eq!(attempt.play(7, 8), Err("Cell already occupied"));
+        assert_eq!(attempt.play(8, 7), Ok(PuzzleVerdict::Solved));
+        assert!(attempt.is_solved());
+        assert!(attempt.solution().is_empty());
+
+        // A four that White can block without harm does not win.
+        let mut attempt = PuzzleAttempt::new(puzzle);
+        assert_eq!(attempt.play(7, 8), Ok(PuzzleVerdict::Wrong));
+
+        // The open four may be made at either end of the three.
+        let puzzle = Puzzle::parse_all(OPEN_THREE).unwrap().remove(0);
+        let mut attempt = PuzzleAttempt::new(puzzle);
+        let Ok(PuzzleVerdict::Correct { reply }) = attempt.play(7, 4) else {
+            panic!("the other end of the three should win too");
+        };
+        assert!(reply == (7, 3) || reply == (7, 8));
+        let finish = attempt.solution();
+        assert_eq!(attempt.play(finish[0].0, finish[0].1), Ok(PuzzleVerdict::Solved));
+    }
+
+    #[test]
+    /// Positions with a single winning first move become rated puzzles;
+    /// positions with several do not.
+    fn mining_keeps_unique_wins() {
+        let limits = VctLimits::default();
+        let crossing = Puzzle::parse_all(CROSSING_FOURS).unwrap().remove(0);
+        let mut game = crossing.position.clone();
+        game.push_move(0, 0).unwrap();
+        game.push_move(0, 14).unwrap();
+        let mined = Puzzle::mine(&game, "Mined", &limits).expect("the double four is unique");
+        assert_eq!((mined.name.as_str(), mined.goal), ("Mined", PuzzleGoal::Vcf));
+        assert_eq!(mined.solutions[0][0], (7, 7));
+        assert_eq!(mined.rating, Some(1));
+        assert!(mined.position.moves().is_empty());
+        assert_eq!(mined.position.board, game.board);
+
+        let four_three = &Puzzle::builtin()[3];
+        let mined = Puzzle::mine(&four_three.position, "", &limits).unwrap();
+        assert_eq!(mined.solutions[0][0], four_three.solutions[0][0]);
+        assert_eq!(mined.rating, Some(2));
+
+        let open_three = Puzzle::parse_all(OPEN_THREE).unwrap().remove(0);
+        assert!(Puzzle::mine(&open_three.position, "", &limits).is_none());
+        // White to move has nothing, and Black's win is not White's puzzle.
+        let mut game = crossing.position.clone();
+        game.switch_player();
+        assert!(Puzzle::mine(&game, "", &limits).is_none());
+    }
+
+    #[test]
+    /// Every built-in puzzle is solved by the search, and every move of
+    /// the solver in a listed solution is proved to win.
+    fn builtin_puzzles_are_sound() {
+        let puzzles = Puzzle::builtin();
+        assert!(puzzles.len() >= 8);
+        for puzzle in puzzles {
+            let player = puzzle.player();
+            assert!(puzzle.solve().is_some(), "{} is not solved", puzzle.name);
+            for solution in &puzzle.solutions {
+                let mut game = puzzle.position.clone();
+                for (i, &(row, col)) in solution.iter().enumerate().step_by(2) {
+                    let proof = game.threat_search_from(
+                        player,
+                        (row, col),
+                        &SOLVE_LIMITS,
+                        puzzle.goal == PuzzleGoal::Vct,
+                    );
+                    let wins = match proof.line {
+                        Some(line) if puzzle.goal == PuzzleGoal::Five => line.len() == 1,
+                        line => line.is_some(),
+                    };
+                    assert!(wins, "{}: ({}, {}) does not win", puzzle.name, row, col);
+                    game.push_move(row, col).unwrap();
+                    if let Some(&(r, c)) = solution.get(i + 1) {
+                        game.push_move(r, c).unwrap();
+                    }
+                }
+                assert_eq!(game.check_winner(), Some(player), "{}", puzzle.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! The eight rotations and reflections of the board.
+//!
+//! Gomoku positions that differ only by a symmetry of the square board
+//! are equivalent. The canonical form of a position is its image with the
+//! smallest Zobrist hash, which lets opening books, transposition tables
+//! and game databases store each position once.
+//!
+//! [`Gomoku::canonical`] returns the canonical form together with the
+//! symmetry that produced it; a move found in the canonical form is played
+//! in the original position by mapping it back with
+//! [`Symmetry::apply_inverse`].
+
+use crate::{BOARD_SIZE, Cell, Gomoku, zobrist};
+
+/// A rotation or reflection of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    /// Quarter turn clockwise.
+    Rotate90,
+    Rotate180,
+    /// Quarter turn counterclockwise.
+    Rotate270,
+    /// Mirror left to right.
+    FlipHorizontal,
+    /// Mirror top to bottom.
+    FlipVertical,
+    /// Mirror in the diagonal from the top left corner.
+    FlipDiagonal,
+    /// Mirror in the diagonal from the top right corner.
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    /// Every symmetry, starting with the identity.
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::FlipDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    /// Image of a cell or move.
+    pub fn apply(self, (row, col): (usize, usize)) -> (usize, usize) {
+        let last = BOARD_SIZE - 1;
+        match self {
+            Symmetry::Identity => (row, col),
+            Symmetry::Rotate90 => (col, last - row),
+            Symmetry::Rotate180 => (last - row, last - col),
+            Symmetry::Rotate270 => (last - col, row),
+            Symmetry::FlipHorizontal => (row, last - col),
+            Symmetry::FlipVertical => (last - row, col),
+            Symmetry::FlipDiagonal => (col, row),
+            Symmetry::FlipAntiDiagonal => (last - col, last - row),
+        }
+    }
+
+    /// The symmetry that undoes this one.
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// The cell whose image is `cell`: maps a move in a transformed
+    /// position back to the original one.
+    pub fn apply_inverse(self, cell: (usize, usize)) -> (usize, usize) {
+        self.inverse().apply(cell)
+    }
+}
+
+impl Gomoku {
+    /// The position transformed by `symmetry`, with the same player to
+    /// move, evaluation weights and move history, each move mapped to its
+    /// image.
+    pub fn transformed(&self, symmetry: Symmetry) -> Gomoku {
+        let mut image = Gomoku::new();
+        image.params = self.params;
+        image.current_player = self.current_player;
+        for (row, col) in self.bits.stones() {
+            if !self.history.contains(&(row, col)) {
+                let (r, c) = symmetry.apply((row, col));
+                image.set_cell(r, c, self.board[row][col]);
+            }
+        }
+        for &(row, col) in &self.history {
+            let (r, c) = symmetry.apply((row, col));
+            image.set_cell(r, c, self.board[row][col]);
+            image.history.push((r, c));
+        }
+        image
+    }
+
+    /// The canonical form of the position and the symmetry that maps the
+    /// position onto it. Equivalent positions have the same canonical
+    /// form.
+    pub fn canonical(&self) -> (Gomoku, Symmetry) {
+        let symmetry = self.canonical_hash().1[0];
+        (self.transformed(symmetry), symmetry)
+    }
+
+    /// Hash of the position transformed by `symmetry`, as
+    /// [`Gomoku::hash`] would compute it for the transformed board.
+    pub(crate) fn hash_with(&self, symmetry: Symmetry) -> u64 {
+        let mut hash = if self.current_player == Cell::White {
+            zobrist::WHITE_TO_MOVE
+        } else {
+            0
+        };
+        for (row, col) in self.bits.stones() {
+            let (r, c) = symmetry.apply((row, col));
+            hash ^= zobrist::stone(self.board[row][col], r, c);
+        }
+        hash
+    }
+
+    /// Hash of the canonical form of the position and every symmetry that
+    /// maps the position onto it, identity first if it is among them.
+    pub(crate) fn canonical_hash(&self) -> (u64, Vec<Symmetry>) {
+        let hashes = Symmetry::ALL.map(|s| self.hash_with(s));
+        let min = *hashes.iter().min().expect("there are eight symmetries");
+        let symmetries = Symmetry::ALL
+            .into_iter()
+            .zip(hashes)
+            .filter(|&(_, hash)| hash == min)
+            .map(|(s, _)| s)
+            .collect();
+        (min, symmetries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    /// A position of `stones` random moves, some of them placed without
+    /// history.
+    fn random_position(rng: &mut Rng, stones: usize) -> Gomoku {
+        let mut game = Gomoku::new();
+        while game.bits.stone_count() < stones {
+            let (row, col) = (rng.below(BOARD_SIZE), rng.below(BOARD_SIZE));
+            if game.board[row][col] != Cell::Empty {
+                continue;
+            }
+            if rng.below(4) == 0 {
+                game.set_cell(row, col, Cell::White);
+            } else {
+                game.push_move(row, col).unwrap();
+            }
+        }
+        game
+    }
+
+    #[test]
+    /// Every symmetry is a permutation of the cells undone by its inverse,
+    /// and the eight are closed under composition.
+    fn symmetries_form_a_group() {
+        for symmetry in Symmetry::ALL {
+            let mut seen = [[false; BOARD_SIZE]; BOARD_SIZE];
+            for row in 0..BOARD_SIZE {
+                for col in 0..BOARD_SIZE {
+                    let (r, c) = symmetry.apply((row, col));
+                    assert!(!seen[r][c], "{:?} maps two cells to ({}, {})", symmetry, r, c);
+                    seen[r][c] = true;
+                    assert_eq!(symmetry.apply_inverse((r, c)), (row, col));
+                }
+            }
+            for other in Symmetry::ALL {
+                let cells = [(0, 1), (2, 5)];
+                let composed = cells.map(|cell| other.apply(symmetry.apply(cell)));
+                assert!(Symmetry::ALL.iter().any(|s| cells.map(|cell| s.apply(cell)) == composed));
+            }
+        }
+    }
+
+    #[test]
+    /// Transformed positions keep their shapes, evaluation, winner and
+    /// history, and round-trip through the inverse.
+    fn transforms_preserve_positions() {
+        let mut rng = Rng::new(44);
+        for round in 0..40 {
+            let game = random_position(&mut rng, round % 30);
+            for symmetry in Symmetry::ALL {
+                let image = game.transformed(symmetry);
+                assert_eq!(image.hash(), game.hash_with(symmetry));
+                assert_eq!(image.current_player(), game.current_player());
+                assert_eq!(image.check_winner(), game.check_winner());
+                for player in [Cell::Black, Cell::White] {
+                    assert_eq!(image.shape_counts(player), game.shape_counts(player));
+                    assert_eq!(image.evaluate(player), game.evaluate(player));
+                    assert_eq!(image.evaluate_incremental(player), image.evaluate(player));
+                }
+                let moves: Vec<_> = game.moves().iter().map(|&mv| symmetry.apply(mv)).collect();
+                assert_eq!(image.moves(), &moves[..]);
+
+                let back = image.transformed(symmetry.inverse());
+                assert_eq!(back.board, game.board);
+                assert_eq!(back.moves(), game.moves());
+            }
+        }
+    }
+
+    #[test]
+    /// All images of a position share one canonical form, and moves in it
+    /// map back to the matching cells of each image.
+    fn canonical_form_is_shared() {
+        let mut rng = Rng::new(7);
+        for round in 0..40 {
+            let game = random_position(&mut rng, round % 12);
+            let (canonical, _) = game.canonical();
+            assert_eq!(canonical.hash(), game.canonical_hash().0);
+            for symmetry in Symmetry::ALL {
+                let image = game.transformed(symmetry);
+                let (form, to_canonical) = image.canonical();
+                assert_eq!(form.board, canonical.board);
+                assert_eq!(form.hash(), canonical.hash());
+                assert_eq!(form.canonical().1, Symmetry::Identity);
+
+                let (row, col) = (rng.below(BOARD_SIZE), rng.below(BOARD_SIZE));
+                let (r, c) = to_canonical.apply_inverse((row, col));
+                assert_eq!(image.board[r][c], form.board[row][col]);
+            }
+        }
+    }
+}
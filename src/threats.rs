@@ -0,0 +1,354 @@
+//! Threats on the board, for hints and teaching.
+//!
+//! A threat is a group of one player's stones along a row, column or
+//! diagonal that has won or is about to: a five, a four that completes
+//! five with one more stone, or an open three that becomes an open four.
+//! Fours include broken fours such as `XX_XX`, and open threes include
+//! broken threes such as `_X_XX_`. Every threat lists the empty cells where
+//! the opponent can stop it, so a front end can point out both the danger
+//! and the answer.
+//!
+//! Threats are found line by line from the stretches of five cells free of
+//! the opponent's stones, independently of the evaluation's shape tables.
+
+use std::fmt;
+
+use crate::vct::offset;
+use crate::{BOARD_SIZE, Cell, DIRECTIONS, Gomoku, WIN_LENGTH};
+
+/// How close a [`Threat`] is to winning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ThreatKind {
+    /// Five or more in a row: the game is won.
+    Five,
+    /// A four that completes five on either of two cells and can no longer
+    /// be stopped.
+    OpenFour,
+    /// A four that completes five on a single cell.
+    Four,
+    /// Three stones that become an open four with one more stone.
+    OpenThree,
+}
+
+impl ThreatKind {
+    /// Every kind, strongest first.
+    pub const ALL: [ThreatKind; 4] = [
+        ThreatKind::Five,
+        ThreatKind::OpenFour,
+        ThreatKind::Four,
+        ThreatKind::OpenThree,
+    ];
+
+    /// Name of the kind, e.g. `open_three`, matching [`Shape::name`](crate::Shape::name).
+    pub fn name(self) -> &'static str {
+        match self {
+            ThreatKind::Five => "five",
+            ThreatKind::OpenFour => "open_four",
+            ThreatKind::Four => "four",
+            ThreatKind::OpenThree => "open_three",
+        }
+    }
+}
+
+/// A threat of one player along one line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Threat {
+    pub kind: ThreatKind,
+    /// The player making the threat.
+    pub player: Cell,
+    /// The stones forming the threat, in order along the line.
+    pub stones: Vec<(usize, usize)>,
+    /// Empty cells where the opponent stops the threat. For a four these
+    /// are the cells completing five; an open four has two of them and
+    /// cannot be stopped, and a five has none.
+    pub defenses: Vec<(usize, usize)>,
+}
+
+/// Formats as e.g. `Black open three (7, 5) (7, 6) (7, 7), defend at
+/// (7, 4) (7, 8)`.
+impl fmt::Display for Threat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}", self.player, self.kind.name().replace('_', " "))?;
+        for &(row, col) in &self.stones {
+            write!(f, " ({}, {})", row, col)?;
+        }
+        if !self.defenses.is_empty() {
+            write!(f, ", defend at")?;
+            for &(row, col) in &self.defenses {
+                write!(f, " ({}, {})", row, col)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A threat found in one line, with cells given by their index along it.
+type LineThreat = (ThreatKind, Vec<usize>, Vec<usize>);
+
+/// A four within a line: its stones and the empty cells completing five
+/// with them.
+struct LineFour {
+    stones: Vec<usize>,
+    points: Vec<usize>,
+}
+
+/// Cells of every row, column and diagonal long enough to hold a five.
+fn board_lines() -> Vec<Vec<(usize, usize)>> {
+    let mut lines = Vec::new();
+    for &dir in &DIRECTIONS {
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                // Lines start at the cells whose predecessor is off the board.
+                if offset((row, col), dir, -1).is_some() {
+                    continue;
+                }
+                let mut line = vec![(row, col)];
+                while let Some(next) = offset(line[line.len() - 1], dir, 1) {
+                    line.push(next);
+                }
+                if line.len() >= WIN_LENGTH {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Fours of `player` along a line, each found from the stretches of five
+/// cells holding four of its stones and one empty cell.
+fn fours(cells: &[Cell], player: Cell) -> Vec<LineFour> {
+    let mut fours: Vec<LineFour> = Vec::new();
+    for start in 0..=cells.len() - WIN_LENGTH {
+        let span = start..start + WIN_LENGTH;
+        if cells[span.clone()].contains(&player.opponent()) {
+            continue;
+        }
+        let stones: Vec<usize> = span.clone().filter(|&i| cells[i] == player).collect();
+        if stones.len() != WIN_LENGTH - 1 {
+            continue;
+        }
+        let point = span
+            .into_iter()
+            .find(|&i| cells[i] == Cell::Empty)
+            .expect("one cell of the stretch is empty");
+        match fours.iter_mut().find(|four| four.stones == stones) {
+            Some(four) if !four.points.contains(&point) => four.points.push(point),
+            Some(_) => {}
+            None => fours.push(LineFour {
+                stones,
+                points: vec![point],
+            }),
+        }
+    }
+    fours
+}
+
+/// Whether one more stone of `player` turns `three` into an open four.
+fn makes_open_four(cells: &mut [Cell], player: Cell, three: &[usize]) -> bool {
+    (0..cells.len()).any(|i| {
+        if cells[i] != Cell::Empty {
+            return false;
+        }
+        cells[i] = player;
+        let open = fours(cells, player)
+            .iter()
+            .any(|four| four.points.len() >= 2 && three.iter().all(|s| four.stones.contains(s)));
+        cells[i] = Cell::Empty;
+        open
+    })
+}
+
+/// Threats of `player` along a line.
+fn line_threats(cells: &mut [Cell], player: Cell) -> Vec<LineThreat> {
+    let mut found: Vec<LineThreat> = Vec::new();
+    let mut start = 0;
+    while start < cells.len() {
+        let end = (start..cells.len())
+            .find(|&i| cells[i] != player)
+            .unwrap_or(cells.len());
+        if end - start >= WIN_LENGTH {
+            found.push((ThreatKind::Five, (start..end).collect(), Vec::new()));
+        }
+        start = end + 1;
+    }
+
+    let fives = found.len();
+    for four in fours(cells, player) {
+        // Four stones of a five are not a four of their own.
+        if found[..fives]
+            .iter()
+            .any(|(_, stones, _)| four.stones.iter().all(|s| stones.contains(s)))
+        {
+            continue;
+        }
+        let kind = if four.points.len() >= 2 {
+            ThreatKind::OpenFour
+        } else {
+            ThreatKind::Four
+        };
+        found.push((kind, four.stones, four.points));
+    }
+
+    // Threes are the stones of the open fours one more stone would make,
+    // unless they already belong to a five or a four.
+    let mut threes: Vec<Vec<usize>> = Vec::new();
+    for i in 0..cells.len() {
+        if cells[i] != Cell::Empty {
+            continue;
+        }
+        cells[i] = player;
+        for four in fours(cells, player) {
+            if four.points.len() < 2 || !four.stones.contains(&i) {
+                continue;
+            }
+            let three: Vec<usize> = four.stones.into_iter().filter(|&s| s != i).collect();
+            let covered = found
+                .iter()
+                .any(|(_, stones, _)| three.iter().all(|s| stones.contains(s)));
+            if !covered && !threes.contains(&three) {
+                threes.push(three);
+            }
+        }
+        cells[i] = Cell::Empty;
+    }
+    for three in threes {
+        let defenses = (0..cells.len())
+            .filter(|&d| {
+                if cells[d] != Cell::Empty {
+                    return false;
+                }
+                cells[d] = player.opponent();
+                let stopped = !makes_open_four(cells, player, &three);
+                cells[d] = Cell::Empty;
+                stopped
+            })
+            .collect();
+        found.push((ThreatKind::OpenThree, three, defenses));
+    }
+    found
+}
+
+impl Gomoku {
+    /// Fives, fours and open threes of `player`, strongest first, each
+    /// with the cells where the opponent can stop it.
+    pub fn threats(&self, player: Cell) -> Vec<Threat> {
+        let mut threats = Vec::new();
+        for line in board_lines() {
+            let mut cells: Vec<Cell> = line.iter().map(|&(row, col)| self.board[row][col]).collect();
+            if cells.iter().filter(|&&cell| cell == player).count() < WIN_LENGTH - 2 {
+                continue;
+            }
+            for (kind, stones, defenses) in line_threats(&mut cells, player) {
+                threats.push(Threat {
+                    kind,
+                    player,
+                    stones: stones.into_iter().map(|i| line[i]).collect(),
+                    defenses: defenses.into_iter().map(|i| line[i]).collect(),
+                });
+            }
+        }
+        threats.sort_by(|a, b| (a.kind, &a.stones).cmp(&(b.kind, &b.stones)));
+        threats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(game: &mut Gomoku, player: Cell, cells: &[(usize, usize)]) {
+        for &(row, col) in cells {
+            game.set_cell(row, col, player);
+        }
+    }
+
+    #[test]
+    /// An open three in the middle of the board is stopped next to its
+    /// ends; near the edge the far cell stops it as well.
+    fn open_three_defenses() {
+        let mut game = Gomoku::new();
+        place(&mut game, Cell::Black, &[(7, 5), (7, 6), (7, 7)]);
+        let expected = Threat {
+            kind: ThreatKind::OpenThree,
+            player: Cell::Black,
+            stones: vec![(7, 5), (7, 6), (7, 7)],
+            defenses: vec![(7, 4), (7, 8)],
+        };
+        assert_eq!(game.threats(Cell::Black), vec![expected]);
+        assert!(game.threats(Cell::White).is_empty());
+
+        let mut game = Gomoku::new();
+        place(&mut game, Cell::White, &[(1, 3), (2, 3), (3, 3)]);
+        let threats = game.threats(Cell::White);
+        assert_eq!(threats.len(), 1);
+        assert_eq!(threats[0].defenses, vec![(0, 3), (4, 3), (5, 3)]);
+
+        // A blocked three is no threat.
+        game.set_cell(4, 3, Cell::Black);
+        assert!(game.threats(Cell::White).is_empty());
+    }
+
+    #[test]
+    /// Fives, open fours, closed and broken fours are told apart.
+    fn fours_and_fives() {
+        let mut game = Gomoku::new();
+        place(&mut game, Cell::Black, &[(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]);
+        place(&mut game, Cell::Black, &[(4, 3), (4, 4), (4, 5), (4, 6)]);
+        place(&mut game, Cell::Black, &[(8, 1), (8, 2), (8, 3), (8, 4)]);
+        place(&mut game, Cell::White, &[(8, 0)]);
+        place(&mut game, Cell::Black, &[(12, 5), (12, 6), (12, 8), (12, 9)]);
+
+        let summary: Vec<_> = game
+            .threats(Cell::Black)
+            .into_iter()
+            .map(|t| (t.kind, t.stones[0], t.defenses))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ThreatKind::Five, (0, 0), vec![]),
+                (ThreatKind::OpenFour, (4, 3), vec![(4, 2), (4, 7)]),
+                (ThreatKind::Four, (8, 1), vec![(8, 5)]),
+                (ThreatKind::Four, (12, 5), vec![(12, 7)]),
+            ]
+        );
+    }
+
+    #[test]
+    /// Playing on any defense of a four or open three removes the threat
+    /// in positions from random games.
+    fn defenses_stop_threats() {
+        let mut rng = crate::Rng::new(46);
+        let mut checked = 0;
+        for _ in 0..30 {
+            let mut game = Gomoku::new();
+            while game.bits.stone_count() < 24 && game.check_winner().is_none() {
+                let (row, col) = (4 + rng.below(7), 4 + rng.below(7));
+                let _ = game.push_move(row, col);
+            }
+            for player in [Cell::Black, Cell::White] {
+                for threat in game.threats(player) {
+                    if !matches!(threat.kind, ThreatKind::Four | ThreatKind::OpenThree) {
+                        continue;
+                    }
+                    assert!(!threat.defenses.is_empty(), "{}", threat);
+                    for &(row, col) in &threat.defenses {
+                        let mut defended = game.clone();
+                        defended.set_cell(row, col, player.opponent());
+                        let remaining = defended.threats(player);
+                        assert!(
+                            !remaining.iter().any(|t| t.stones == threat.stones),
+                            "{} survives ({}, {})",
+                            threat,
+                            row,
+                            col
+                        );
+                        checked += 1;
+                    }
+                }
+            }
+        }
+        assert!(checked > 0);
+    }
+}
@@ -0,0 +1,253 @@
+//! Fitting the evaluation weights to self-play results with Texel's
+//! method.
+//!
+//! Every position of a self-play game is labelled with the final result
+//! from the point of view of the player to move: 1 for a win, 0.5 for a
+//! draw and 0 for a loss. The evaluation is turned into a predicted result
+//! with a logistic curve, `1 / (1 + exp(-scale * eval))`, and the weights
+//! are nudged one at a time for as long as the mean squared error between
+//! predictions and results goes down. The weight of a five is a win and
+//! stays fixed; every other weight stays below it.
+//!
+//! Positions in which the player to move has a four are left out, because
+//! their result is decided by the next move rather than by the shapes on
+//! the board.
+
+use crate::rng::Rng;
+use crate::{
+    BOARD_SIZE, Engine, EvalParams, Gomoku, MinimaxEngine, SHAPE_COUNT, SearchLimits, Shape,
+};
+
+/// A position reduced to what the evaluation sees, with the result of its
+/// game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// Shape counts of the player to move minus those of the opponent,
+    /// indexed by [`Shape::index`].
+    pub features: [i32; SHAPE_COUNT],
+    /// Final result for the player to move.
+    pub result: f64,
+}
+
+impl Sample {
+    /// Describe `game` from the point of view of the player to move.
+    pub fn new(game: &Gomoku, result: f64) -> Self {
+        let player = game.current_player();
+        let own = game.shape_counts(player);
+        let theirs = game.shape_counts(player.opponent());
+        let mut features = [0; SHAPE_COUNT];
+        for (i, feature) in features.iter_mut().enumerate() {
+            *feature = own[i] as i32 - theirs[i] as i32;
+        }
+        Sample { features, result }
+    }
+
+    /// Evaluation of the position with `params`.
+    pub fn evaluate(&self, params: &EvalParams) -> i32 {
+        self.features
+            .iter()
+            .zip(params.weights.iter())
+            .map(|(&f, &w)| f * w)
+            .sum()
+    }
+}
+
+/// How [`SelfPlay::play_game`] plays its games.
+#[derive(Clone, Copy, Debug)]
+pub struct SelfPlay {
+    /// Search depth of the minimax engine.
+    pub depth: u32,
+    /// Random moves near the center that open each game, so that games
+    /// differ from each other.
+    pub random_plies: usize,
+    /// Seed of the opening moves; game `n` uses `seed + n`.
+    pub seed: u64,
+}
+
+impl Default for SelfPlay {
+    fn default() -> Self {
+        SelfPlay {
+            depth: 1,
+            random_plies: 4,
+            seed: 0,
+        }
+    }
+}
+
+impl SelfPlay {
+    /// Play game number `index` with both sides using `params` and return
+    /// its positions after the opening, labelled with the result.
+    pub fn play_game(&self, params: &EvalParams, index: u64) -> Vec<Sample> {
+        let mut rng = Rng::new(self.seed.wrapping_add(index));
+        let mut engine = MinimaxEngine::single_threaded();
+        let limits = SearchLimits {
+            depth: Some(self.depth),
+            ..SearchLimits::default()
+        };
+        let mut game = Gomoku::new();
+        game.set_eval_params(*params);
+
+        let center = BOARD_SIZE / 2;
+        let mut positions = Vec::new();
+        let mut plies = 0;
+        while game.check_winner().is_none() && !game.is_board_full() {
+            let (row, col) = if plies < self.random_plies {
+                let mut spot = || center - 3 + rng.below(7);
+                (spot(), spot())
+            } else {
+                if is_quiet(&game) {
+                    positions.push(game.clone());
+                }
+                engine.choose_move(&game, &limits).best_move
+            };
+            if game.push_move(row, col).is_ok() {
+                plies += 1;
+            }
+        }
+
+        let winner = game.check_winner();
+        positions
+            .iter()
+            .map(|position| {
+                let result = match winner {
+                    Some(w) if w == position.current_player() => 1.0,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                Sample::new(position, result)
+            })
+            .collect()
+    }
+}
+
+/// Whether the player to move has no four to win with.
+fn is_quiet(game: &Gomoku) -> bool {
+    let own = game.shape_counts(game.current_player());
+    [Shape::OpenFour, Shape::Four, Shape::BrokenFour]
+        .iter()
+        .all(|shape| own[shape.index()] == 0)
+}
+
+fn predict(eval: i32, scale: f64) -> f64 {
+    1.0 / (1.0 + (-scale * eval as f64).exp())
+}
+
+/// Mean squared error of the predicted results.
+pub fn mean_error(samples: &[Sample], params: &EvalParams, scale: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let total: f64 = samples
+        .iter()
+        .map(|s| (s.result - predict(s.evaluate(params), scale)).powi(2))
+        .sum();
+    total / samples.len() as f64
+}
+
+/// The scale of the logistic curve that best fits `params`, searched on a
+/// logarithmic grid between 1e-6 and 1.
+pub fn fit_scale(samples: &[Sample], params: &EvalParams) -> f64 {
+    (0..=120)
+        .map(|i| 10f64.powf(-6.0 + i as f64 / 20.0))
+        .min_by(|&a, &b| {
+            mean_error(samples, params, a).total_cmp(&mean_error(samples, params, b))
+        })
+        .unwrap_or(1e-3)
+}
+
+/// Improve `params` by local search: each round tries raising and lowering
+/// every weight by a tenth and keeps changes that lower the error. Weights
+/// never reach the weight of a five. Stops after `rounds` rounds or when a
+/// round changes nothing.
+///
+/// Returns the tuned parameters and their error.
+pub fn tune_params(samples: &[Sample], params: &EvalParams, scale: f64, rounds: usize) -> (EvalParams, f64) {
+    let mut best = *params;
+    let mut error = mean_error(samples, &best, scale);
+    let limit = best.weight(Shape::Five).abs();
+    for _ in 0..rounds {
+        let mut improved = false;
+        for shape in Shape::ALL.into_iter().filter(|&s| s != Shape::Five) {
+            let weight = best.weight(shape);
+            let step = (weight.abs() / 10).max(1);
+            for candidate in [weight + step, weight - step] {
+                if candidate.abs() >= limit {
+                    continue;
+                }
+                let mut trial = best;
+                trial.set_weight(shape, candidate);
+                let trial_error = mean_error(samples, &trial, scale);
+                if trial_error < error {
+                    best = trial;
+                    error = trial_error;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    (best, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sample that differs from an even position by `count` of `shape`.
+    fn sample(shape: Shape, count: i32, result: f64) -> Sample {
+        let mut features = [0; SHAPE_COUNT];
+        features[shape.index()] = count;
+        Sample { features, result }
+    }
+
+    #[test]
+    /// Features are taken from the side to move.
+    fn sample_uses_side_to_move() {
+        let mut game = Gomoku::new();
+        game.push_move(7, 7).unwrap();
+        game.push_move(0, 0).unwrap();
+        game.push_move(7, 8).unwrap();
+        let sample = Sample::new(&game, 0.0);
+        assert_eq!(sample.features[Shape::OpenTwo.index()], -1);
+        assert_eq!(
+            sample.evaluate(&EvalParams::default()),
+            -Shape::OpenTwo.weight()
+        );
+    }
+
+    #[test]
+    /// A shape that always wins gains weight and one that always loses
+    /// drops below zero.
+    fn tuning_follows_results() {
+        let mut samples = Vec::new();
+        for _ in 0..10 {
+            samples.push(sample(Shape::SplitTwo, 1, 1.0));
+            samples.push(sample(Shape::SplitTwo, -1, 0.0));
+            samples.push(sample(Shape::ClosedTwo, 1, 0.0));
+            samples.push(sample(Shape::ClosedTwo, -1, 1.0));
+        }
+        let params = EvalParams::default();
+        let scale = 0.01;
+        let (tuned, error) = tune_params(&samples, &params, scale, 100);
+        assert!(error < mean_error(&samples, &params, scale));
+        assert!(tuned.weight(Shape::SplitTwo) > params.weight(Shape::SplitTwo));
+        assert!(tuned.weight(Shape::ClosedTwo) < 0);
+        assert_eq!(tuned.weight(Shape::Five), params.weight(Shape::Five));
+    }
+
+    #[test]
+    /// Self-play labels every recorded position with a valid result.
+    fn self_play_records_positions() {
+        let config = SelfPlay {
+            random_plies: 6,
+            ..SelfPlay::default()
+        };
+        let samples = config.play_game(&EvalParams::default(), 0);
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|s| [0.0, 0.5, 1.0].contains(&s.result)));
+        assert_eq!(samples, config.play_game(&EvalParams::default(), 0));
+    }
+}
@@ -0,0 +1,621 @@
+//! Threat-space search for forced wins.
+//!
+//! The search only considers attacking moves that create a four (a threat
+//! to complete five on the next move) or, when enabled, a three (a threat to
+//! create an open four). The defender answers every threat with all moves
+//! that could stop it, including counter-fours of its own, so a returned
+//! line is a forced win rather than a hopeful sequence.
+
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::clock::Stopwatch;
+use crate::{BOARD_SIZE, Cell, DIRECTIONS, Gomoku, WIN_LENGTH};
+
+/// Number of cells on either side of a stone that can share a five with it.
+const REACH: usize = WIN_LENGTH - 1;
+/// Length of the line segment centred on a cell that is inspected for
+/// threats through that cell.
+const WINDOW: usize = 2 * REACH + 1;
+
+/// Limits for a threat-space search.
+#[derive(Clone, Copy, Debug)]
+pub struct VctLimits {
+    /// Maximum number of attacking moves before the final five or open four.
+    pub max_depth: usize,
+    /// Maximum number of positions visited before the search gives up.
+    pub max_nodes: usize,
+}
+
+impl Default for VctLimits {
+    fn default() -> Self {
+        VctLimits {
+            max_depth: 5,
+            max_nodes: 5000,
+        }
+    }
+}
+
+/// Number of positions visited between checks of a [`VctInterrupt`].
+const INTERRUPT_INTERVAL: usize = 256;
+
+/// What cuts a threat-space search short besides its node limit. An
+/// interrupted search gives up as if it had run out of nodes.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct VctInterrupt<'a> {
+    /// Flag that stops the search once it becomes true.
+    pub(crate) stop: Option<&'a AtomicBool>,
+    /// Clock and the number of milliseconds after which the search stops.
+    pub(crate) deadline: Option<(&'a Stopwatch, u64)>,
+}
+
+impl VctInterrupt<'_> {
+    fn interrupted(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+            || self
+                .deadline
+                .is_some_and(|(clock, limit)| clock.elapsed_ms() >= limit)
+    }
+}
+
+/// Outcome of a threat-space search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VctResult {
+    /// The proof line alternating attacker and defender moves, starting
+    /// and ending with the attacker. `None` when no forced win was found
+    /// within the limits.
+    pub line: Option<Vec<(usize, usize)>>,
+    /// Number of positions visited.
+    pub nodes: usize,
+}
+
+/// A cell of a line window seen from the side whose threats are examined.
+#[derive(Clone, Copy, PartialEq)]
+enum Spot {
+    Own,
+    Empty,
+    Blocked,
+}
+
+type Window = [Spot; WINDOW];
+
+/// Whether the window contains `WIN_LENGTH` consecutive own stones.
+fn has_five(window: &Window) -> bool {
+    window
+        .windows(WIN_LENGTH)
+        .any(|run| run.iter().all(|&s| s == Spot::Own))
+}
+
+/// Number of empty cells in the window that would complete a five.
+fn five_points(window: &Window) -> usize {
+    let mut probe = *window;
+    let mut count = 0;
+    for i in 0..WINDOW {
+        if probe[i] != Spot::Empty {
+            continue;
+        }
+        probe[i] = Spot::Own;
+        if has_five(&probe) {
+            count += 1;
+        }
+        probe[i] = Spot::Empty;
+    }
+    count
+}
+
+/// Whether one more stone in the window can produce two five points,
+/// i.e. the window holds a three that can become an open four.
+fn has_three(window: &Window) -> bool {
+    let mut probe = *window;
+    for i in 0..WINDOW {
+        if probe[i] != Spot::Empty {
+            continue;
+        }
+        probe[i] = Spot::Own;
+        let open_four = five_points(&probe) >= 2;
+        probe[i] = Spot::Empty;
+        if open_four {
+            return true;
+        }
+    }
+    false
+}
+
+/// Threats created by placing a stone on a particular cell.
+#[derive(Clone, Copy)]
+struct ThreatMove {
+    pos: (usize, usize),
+    /// Total number of five points the stone creates across all lines.
+    fours: usize,
+    /// Bit mask of the directions in which the stone creates five points.
+    four_dirs: u8,
+}
+
+pub(crate) fn offset(pos: (usize, usize), dir: (i32, i32), step: i32) -> Option<(usize, usize)> {
+    let r = pos.0 as i32 + dir.0 * step;
+    let c = pos.1 as i32 + dir.1 * step;
+    if r < 0 || r >= BOARD_SIZE as i32 || c < 0 || c >= BOARD_SIZE as i32 {
+        None
+    } else {
+        Some((r as usize, c as usize))
+    }
+}
+
+impl Gomoku {
+    /// Extract the line through `pos` in direction `dir`, treating `pos`
+    /// itself as occupied by `player`.
+    fn window(&self, pos: (usize, usize), dir: (i32, i32), player: Cell) -> Window {
+        let mut window = [Spot::Blocked; WINDOW];
+        for (i, spot) in window.iter_mut().enumerate() {
+            let step = i as i32 - REACH as i32;
+            *spot = match offset(pos, dir, step) {
+                _ if step == 0 => Spot::Own,
+                Some((r, c)) if self.board[r][c] == player => Spot::Own,
+                Some((r, c)) if self.board[r][c] == Cell::Empty => Spot::Empty,
+                _ => Spot::Blocked,
+            };
+        }
+        window
+    }
+
+    /// Whether a stone of `player` on `(row, col)` would complete five in
+    /// a row. The cell itself is assumed to be empty.
+    pub(crate) fn completes_five(&self, row: usize, col: usize, player: Cell) -> bool {
+        DIRECTIONS
+            .iter()
+            .any(|&dir| has_five(&self.window((row, col), dir, player)))
+    }
+
+    /// Whether a stone of `player` on `(row, col)` would complete five
+    /// along the single direction `dir`.
+    pub(crate) fn completes_five_along(
+        &self,
+        row: usize,
+        col: usize,
+        dir: (i32, i32),
+        player: Cell,
+    ) -> bool {
+        has_five(&self.window((row, col), dir, player))
+    }
+
+    /// Number of cells where `player` could complete five along `dir`
+    /// after a stone on the empty cell `(row, col)`: none, one for a four
+    /// and two or more for an open four.
+    pub(crate) fn five_points_along(
+        &self,
+        row: usize,
+        col: usize,
+        dir: (i32, i32),
+        player: Cell,
+    ) -> usize {
+        let window = self.window((row, col), dir, player);
+        if window.iter().filter(|&&s| s == Spot::Own).count() < WIN_LENGTH - 1 {
+            return 0;
+        }
+        five_points(&window)
+    }
+
+    /// Empty cells where `player` would complete five in a row.
+    pub(crate) fn five_points(&self, player: Cell) -> Vec<(usize, usize)> {
+        let mut points = Vec::new();
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                if self.board[row][col] == Cell::Empty && self.completes_five(row, col, player) {
+                    points.push((row, col));
+                }
+            }
+        }
+        points
+    }
+
+    /// Moves that create a four, and optionally a three, for `player`,
+    /// ordered with the strongest threats first.
+    fn threat_moves(&self, player: Cell, threes: bool) -> Vec<ThreatMove> {
+        let mut moves = Vec::new();
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                if self.board[row][col] != Cell::Empty {
+                    continue;
+                }
+                let mut threat = ThreatMove {
+                    pos: (row, col),
+                    fours: 0,
+                    four_dirs: 0,
+                };
+                let mut three = false;
+                for (d, &dir) in DIRECTIONS.iter().enumerate() {
+                    let window = self.window((row, col), dir, player);
+                    let own = window.iter().filter(|&&s| s == Spot::Own).count();
+                    if own >= WIN_LENGTH - 1 {
+                        let points = five_points(&window);
+                        if points > 0 {
+                            threat.fours += points;
+                            threat.four_dirs |= 1 << d;
+                            continue;
+                        }
+                    }
+                    if threes && !three && own >= WIN_LENGTH - 2 {
+                        three = has_three(&window);
+                    }
+                }
+                if threat.fours > 0 || three {
+                    moves.push(threat);
+                }
+            }
+        }
+        moves.sort_by_key(|t| Reverse(t.fours));
+        moves
+    }
+}
+
+struct ThreatSearch<'a> {
+    game: Gomoku,
+    attacker: Cell,
+    threes: bool,
+    nodes: usize,
+    max_nodes: usize,
+    interrupt: VctInterrupt<'a>,
+    interrupted: bool,
+}
+
+impl ThreatSearch<'_> {
+    fn exhausted(&self) -> bool {
+        self.nodes >= self.max_nodes || self.interrupted
+    }
+
+    /// Count a visited position, returning `false` once the budget is spent
+    /// or the search is interrupted.
+    fn visit(&mut self) -> bool {
+        if self.exhausted() {
+            return false;
+        }
+        if self.nodes.is_multiple_of(INTERRUPT_INTERVAL) && self.interrupt.interrupted() {
+            self.interrupted = true;
+            return false;
+        }
+        self.nodes += 1;
+        true
+    }
+
+    /// Attacker to move: find a threat after which every defence loses.
+    fn attack(&mut self, depth: usize) -> Option<Vec<(usize, usize)>> {
+        if !self.visit() {
+            return None;
+        }
+        let attacker = self.attacker;
+        if let Some(&win) = self.game.five_points(attacker).first() {
+            return Some(vec![win]);
+        }
+
+        let blocks = self.game.five_points(attacker.opponent());
+        if blocks.len() > 1 {
+            return None;
+        }
+        let candidates: Vec<(usize, usize)> = if blocks.is_empty() {
+            let threats = self.game.threat_moves(attacker, self.threes);
+            if let Some(open_four) = threats.iter().find(|t| t.fours >= 2) {
+                return Some(self.finish(open_four.pos));
+            }
+            threats.iter().map(|t| t.pos).collect()
+        } else {
+            // The defender threatens five, so the only playable move is the
+            // block; the search continues only if it also creates a threat.
+            blocks
+        };
+        if depth == 0 {
+            return None;
+        }
+
+        for (row, col) in candidates {
+            self.game.set_cell(row, col, attacker);
+            let line = self.defend(depth - 1);
+            self.game.set_cell(row, col, Cell::Empty);
+            if let Some(mut line) = line {
+                line.insert(0, (row, col));
+                return Some(line);
+            }
+            if self.exhausted() {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Defender to move after an attacking threat: every reply must lose.
+    fn defend(&mut self, depth: usize) -> Option<Vec<(usize, usize)>> {
+        if !self.visit() {
+            return None;
+        }
+        let attacker = self.attacker;
+        let defender = attacker.opponent();
+        if !self.game.five_points(defender).is_empty() {
+            return None;
+        }
+
+        let mut replies = self.game.five_points(attacker);
+        if replies.is_empty() {
+            let decisive: Vec<ThreatMove> = self
+                .game
+                .threat_moves(attacker, false)
+                .into_iter()
+                .filter(|t| t.fours >= 2)
+                .collect();
+            if decisive.is_empty() {
+                return None;
+            }
+            for threat in &decisive {
+                replies.push(threat.pos);
+                for (d, &dir) in DIRECTIONS.iter().enumerate() {
+                    if threat.four_dirs & (1 << d) == 0 {
+                        continue;
+                    }
+                    for step in -(REACH as i32)..=REACH as i32 {
+                        if let Some((r, c)) = offset(threat.pos, dir, step)
+                            && self.game.board[r][c] == Cell::Empty
+                        {
+                            replies.push((r, c));
+                        }
+                    }
+                }
+            }
+            replies.extend(
+                self.game
+                    .threat_moves(defender, false)
+                    .into_iter()
+                    .map(|t| t.pos),
+            );
+            replies.sort();
+            replies.dedup();
+        }
+
+        let mut proof: Option<Vec<(usize, usize)>> = None;
+        for (row, col) in replies {
+            self.game.set_cell(row, col, defender);
+            let line = self.attack(depth);
+            self.game.set_cell(row, col, Cell::Empty);
+            let mut line = line?;
+            line.insert(0, (row, col));
+            if proof.as_ref().is_none_or(|p| line.len() > p.len()) {
+                proof = Some(line);
+            }
+        }
+        proof
+    }
+
+    /// Complete the line for a move that leaves two five points.
+    fn finish(&mut self, pos: (usize, usize)) -> Vec<(usize, usize)> {
+        self.game.set_cell(pos.0, pos.1, self.attacker);
+        let points = self.game.five_points(self.attacker);
+        self.game.set_cell(pos.0, pos.1, Cell::Empty);
+        vec![pos, points[0], points[1]]
+    }
+}
+
+impl Gomoku {
+    /// Search for a victory by continuous threats for `player`.
+    ///
+    /// The attacker may use fours and threes; the defender's replies
+    /// include counter-fours. Iterative deepening ensures the shortest
+    /// proof within `limits` is returned.
+    pub fn vct(&self, player: Cell, limits: &VctLimits) -> VctResult {
+        self.threat_search(player, limits, true)
+    }
+
+    /// Search for a victory by continuous fours for `player`.
+    ///
+    /// Like [`Gomoku::vct`] but only fours are used as threats, which is
+    /// much cheaper and leaves the defender a single reply at each step.
+    pub fn vcf(&self, player: Cell, limits: &VctLimits) -> VctResult {
+        self.threat_search(player, limits, false)
+    }
+
+    /// Search for a forced win for `player` that starts with the move
+    /// `first`, like [`Gomoku::vct`] or, without `threes`, like
+    /// [`Gomoku::vcf`], in which case `first` has to make a four.
+    pub(crate) fn threat_search_from(
+        &self,
+        player: Cell,
+        first: (usize, usize),
+        limits: &VctLimits,
+        threes: bool,
+    ) -> VctResult {
+        let mut search = ThreatSearch {
+            game: self.clone(),
+            attacker: player,
+            threes,
+            nodes: 1,
+            max_nodes: limits.max_nodes,
+            interrupt: VctInterrupt::default(),
+            interrupted: false,
+        };
+        let (row, col) = first;
+        if self.completes_five(row, col, player) {
+            return VctResult {
+                line: Some(vec![first]),
+                nodes: search.nodes,
+            };
+        }
+        search.game.set_cell(row, col, player);
+        if threes || !search.game.five_points(player).is_empty() {
+            for depth in 0..limits.max_depth {
+                if let Some(mut line) = search.defend(depth) {
+                    line.insert(0, first);
+                    return VctResult {
+                        line: Some(line),
+                        nodes: search.nodes,
+                    };
+                }
+                if search.exhausted() {
+                    break;
+                }
+            }
+        }
+        VctResult {
+            line: None,
+            nodes: search.nodes,
+        }
+    }
+
+    fn threat_search(&self, player: Cell, limits: &VctLimits, threes: bool) -> VctResult {
+        self.threat_search_until(player, limits, threes, VctInterrupt::default())
+    }
+
+    /// Search like [`Gomoku::vct`] or, without `threes`, like
+    /// [`Gomoku::vcf`], giving up early when `interrupt` says so.
+    pub(crate) fn threat_search_until(
+        &self,
+        player: Cell,
+        limits: &VctLimits,
+        threes: bool,
+        interrupt: VctInterrupt,
+    ) -> VctResult {
+        let mut search = ThreatSearch {
+            game: self.clone(),
+            attacker: player,
+            threes,
+            nodes: 0,
+            max_nodes: limits.max_nodes,
+            interrupt,
+            interrupted: false,
+        };
+        for depth in 0..=limits.max_depth {
+            if let Some(line) = search.attack(depth) {
+                return VctResult {
+                    line: Some(line),
+                    nodes: search.nodes,
+                };
+            }
+            if search.exhausted() {
+                break;
+            }
+        }
+        VctResult {
+            line: None,
+            nodes: search.nodes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play out a proof line from the attacker's side and return the winner.
+    fn replay(game: &Gomoku, player: Cell, line: &[(usize, usize)]) -> Option<Cell> {
+        let mut game = game.clone();
+        let mut color = player;
+        for &(row, col) in line {
+            assert_eq!(game.board[row][col], Cell::Empty);
+            game.set_cell(row, col, color);
+            color = color.opponent();
+        }
+        game.check_winner()
+    }
+
+    #[test]
+    /// Two blocked threes crossing on an empty cell form a double four.
+    fn vcf_finds_double_four() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 2, Cell::White);
+        game.set_cell(2, 6, Cell::White);
+        for col in 3..6 {
+            game.set_cell(7, col, Cell::Black);
+        }
+        for row in 3..6 {
+            game.set_cell(row, 6, Cell::Black);
+        }
+
+        let result = game.vcf(Cell::Black, &VctLimits::default());
+        let line = result.line.expect("double four should be found");
+        assert_eq!(line[0], (7, 6));
+        assert_eq!(replay(&game, Cell::Black, &line), Some(Cell::Black));
+    }
+
+    #[test]
+    /// A double three wins through threes but not through fours alone.
+    fn vct_finds_double_three() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 5, Cell::Black);
+        game.set_cell(7, 6, Cell::Black);
+        game.set_cell(5, 7, Cell::Black);
+        game.set_cell(6, 7, Cell::Black);
+
+        assert!(game.vcf(Cell::Black, &VctLimits::default()).line.is_none());
+        let line = game
+            .vct(Cell::Black, &VctLimits::default())
+            .line
+            .expect("double three should be found");
+        assert_eq!(line[0], (7, 7));
+        assert_eq!(line.len() % 2, 1);
+        assert_eq!(replay(&game, Cell::Black, &line), Some(Cell::Black));
+    }
+
+    #[test]
+    /// A defender with an open three can answer threes with a counter-four.
+    fn counter_four_refutes_threes() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 5, Cell::Black);
+        game.set_cell(7, 6, Cell::Black);
+        game.set_cell(5, 7, Cell::Black);
+        game.set_cell(6, 7, Cell::Black);
+        for col in 2..5 {
+            game.set_cell(12, col, Cell::White);
+        }
+
+        assert!(game.vct(Cell::Black, &VctLimits::default()).line.is_none());
+    }
+
+    #[test]
+    /// A raised stop flag or a spent time budget ends the search before
+    /// it finds the win, and neither changes the result otherwise.
+    fn interrupts_stop_the_search() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 5, Cell::Black);
+        game.set_cell(7, 6, Cell::Black);
+        game.set_cell(5, 7, Cell::Black);
+        game.set_cell(6, 7, Cell::Black);
+        let limits = VctLimits::default();
+        let expected = game.vct(Cell::Black, &limits);
+
+        let stop = AtomicBool::new(true);
+        let clock = Stopwatch::start();
+        for interrupt in [
+            VctInterrupt {
+                stop: Some(&stop),
+                ..VctInterrupt::default()
+            },
+            VctInterrupt {
+                deadline: Some((&clock, 0)),
+                ..VctInterrupt::default()
+            },
+        ] {
+            let result = game.threat_search_until(Cell::Black, &limits, true, interrupt);
+            assert_eq!((result.line, result.nodes), (None, 0));
+        }
+
+        stop.store(false, Ordering::Relaxed);
+        let interrupt = VctInterrupt {
+            stop: Some(&stop),
+            deadline: Some((&clock, 60_000)),
+        };
+        assert_eq!(game.threat_search_until(Cell::Black, &limits, true, interrupt), expected);
+    }
+
+    #[test]
+    /// The node limit stops the search and is reported back.
+    fn node_limit_is_respected() {
+        let mut game = Gomoku::new();
+        game.set_cell(7, 5, Cell::Black);
+        game.set_cell(7, 6, Cell::Black);
+        game.set_cell(5, 7, Cell::Black);
+        game.set_cell(6, 7, Cell::Black);
+
+        let limits = VctLimits {
+            max_depth: 5,
+            max_nodes: 3,
+        };
+        let result = game.vct(Cell::Black, &limits);
+        assert!(result.line.is_none());
+        assert_eq!(result.nodes, 3);
+    }
+}
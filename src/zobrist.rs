@@ -0,0 +1,47 @@
+//! Zobrist keys for hashing board positions.
+//!
+//! Every (colour, cell) pair gets a fixed pseudo-random 64-bit key and a
+//! position hashes to the XOR of the keys of its stones, so placing or
+//! removing a stone updates a hash with a single XOR.
+
+use crate::{BOARD_SIZE, Cell};
+
+const CELLS: usize = BOARD_SIZE * BOARD_SIZE;
+
+/// Advance a SplitMix64 state and return the next output.
+const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn generate() -> [[u64; CELLS]; 2] {
+    let mut keys = [[0; CELLS]; 2];
+    let mut state = 0x676F_6D6F_6B75; // "gomoku"
+    let mut color = 0;
+    while color < 2 {
+        let mut cell = 0;
+        while cell < CELLS {
+            keys[color][cell] = splitmix64(&mut state);
+            cell += 1;
+        }
+        color += 1;
+    }
+    keys
+}
+
+const KEYS: [[u64; CELLS]; 2] = generate();
+
+/// Key mixed into the hash when White is to move.
+pub(crate) const WHITE_TO_MOVE: u64 = 0x5BD1_E995_2C6B_7A4F;
+
+/// Key for a stone of `color` on the given cell. Empty cells hash to zero.
+pub(crate) fn stone(color: Cell, row: usize, col: usize) -> u64 {
+    match color {
+        Cell::Black => KEYS[0][row * BOARD_SIZE + col],
+        Cell::White => KEYS[1][row * BOARD_SIZE + col],
+        Cell::Empty => 0,
+    }
+}
@@ -1,4 +1,4 @@
-import init, { WasmGomoku, board_size } from '../pkg/gomoku.js';
+import init, { WasmGomoku, WasmPuzzle, board_size, puzzle_count } from '../pkg/gomoku.js';
 
 let BOARD_SIZE;
 
@@ -6,8 +6,21 @@ const canvas = document.getElementById('board');
 const startButton = document.getElementById('startButton');
 const messageDiv = document.getElementById('message');
 const infoDiv = document.getElementById('info');
+const searchDiv = document.getElementById('search');
+const analyzeButton = document.getElementById('analyzeButton');
+const stopButton = document.getElementById('stopButton');
+const drawButton = document.getElementById('drawButton');
+const hintButton = document.getElementById('hintButton');
+const analysisTitle = document.getElementById('analysisTitle');
+const analysisLines = document.getElementById('analysisLines');
 const playerFirstRadio = document.getElementById('playerFirst');
 const aiFirstRadio = document.getElementById('aiFirst');
+const engineSelect = document.getElementById('engine');
+const levelSelect = document.getElementById('level');
+const threatsToggle = document.getElementById('threatsToggle');
+const threatsList = document.getElementById('threatsList');
+const puzzleButton = document.getElementById('puzzleButton');
+const solutionButton = document.getElementById('solutionButton');
 
 const gl = canvas.getContext('webgl');
 if (!gl) {
@@ -20,6 +33,16 @@ let recentMoves = [];
 let animRequestId = null;
 let lastMove = null;
 let currentBoard = [];
+let aiWorker = null;
+let aiSettings = null;
+let thinking = false; // whether the worker is searching, for the AI or a hint
+let bestSoFar = null; // latest progress report of the running search
+let lastSearch = null; // result of the last AI move
+let moves = []; // moves played so far, sent to the worker
+let threats = []; // threats of both players shown on the board
+let hint = null; // suggested move shown until the next move
+let puzzle = null; // the puzzle shown instead of a game, if any
+let puzzleIndex = 0; // built-in puzzle started by the next click on Puzzle
 
 const FADE_DURATION = 1000; // ms
 const HIGHLIGHT_DURATION = 2000; // ms
@@ -32,6 +55,12 @@ function endGame(msg) {
     startButton.textContent = 'Restart';
     playerFirstRadio.disabled = false;
     aiFirstRadio.disabled = false;
+    engineSelect.disabled = false;
+    levelSelect.disabled = false;
+    analyzeButton.disabled = true;
+    stopButton.disabled = true;
+    drawButton.disabled = true;
+    hintButton.disabled = true;
 }
 
 // Basic shaders for 2D rendering
@@ -150,10 +179,42 @@ function drawHighlight(row, col, player, alpha) {
     gl.drawArrays(gl.LINE_STRIP, 0, verts.length / 2);
 }
 
+// Mark a cell that stops a threat of `player` with a small square: red
+// against Black's threats, blue against White's.
+function drawMarker(row, col, player) {
+    const [x, y] = ndcFromBoard(row, col);
+    const half = (2 * BOARD_SCALE / BOARD_SIZE) * 0.15;
+    const verts = [x - half, y - half, x + half, y - half, x + half, y + half, x - half, y + half];
+    const buffer = gl.createBuffer();
+    gl.bindBuffer(gl.ARRAY_BUFFER, buffer);
+    gl.bufferData(gl.ARRAY_BUFFER, new Float32Array(verts), gl.STATIC_DRAW);
+    gl.vertexAttribPointer(coord, 2, gl.FLOAT, false, 0, 0);
+    if (player === 1) {
+        gl.uniform4f(colorUniform, 0.85, 0.1, 0.1, 0.9);
+    } else {
+        gl.uniform4f(colorUniform, 0.1, 0.3, 0.85, 0.9);
+    }
+    gl.drawArrays(gl.TRIANGLE_FAN, 0, 4);
+}
+
+// Look up the threats of both players when the overlay is on, list them
+// and redraw the board with their defenses marked.
+function updateThreats() {
+    threats = game && !puzzle && threatsToggle.checked ? [...game.threats(1), ...game.threats(2)] : [];
+    threatsList.replaceChildren(...threats.map((threat) => {
+        const item = document.createElement('li');
+        const player = threat.player === 1 ? 'Black' : 'White';
+        const kind = threat.kind.replace('_', ' ');
+        const defend = threat.defenses.length ? `, defend at ${formatMoves(threat.defenses)}` : '';
+        item.textContent = `${player} ${kind} ${formatMoves(threat.stones)}${defend}`;
+        return item;
+    }));
+    render();
+}
 
 // Convert the flat board array from WebAssembly into a 2D matrix.
 function boardMatrix() {
-    const data = game.board();
+    const data = (puzzle || game).board();
     const board = [];
     for (let r = 0; r < BOARD_SIZE; r++) {
         board[r] = [];
@@ -193,6 +254,15 @@ function render() {
         }
     }
 
+    for (const threat of threats) {
+        for (const [r, c] of threat.defenses) {
+            drawMarker(r, c, threat.player);
+        }
+    }
+    if (hint) {
+        drawHighlight(hint.row, hint.col, 1, 0.6);
+    }
+
     if (lastMove) {
         const elapsed = now - lastMove.time;
         if (elapsed < HIGHLIGHT_DURATION) {
@@ -212,68 +282,247 @@ function render() {
     }
 }
 
+// Start a worker that searches the AI's moves, so that the page stays
+// responsive while the AI thinks.
+function startWorker() {
+    const worker = new Worker(new URL('worker.js', import.meta.url), { type: 'module' });
+    worker.onmessage = (e) => {
+        // Ignore messages still queued from a stopped worker.
+        if (worker !== aiWorker || !thinking) return;
+        const { type, search } = e.data;
+        if (type === 'progress') {
+            bestSoFar = search;
+            showSearch(search);
+            stopButton.disabled = false;
+        } else if (type === 'done') {
+            playAiMove(search);
+        } else if (type === 'hint') {
+            thinking = false;
+            hint = e.data.hint;
+            messageDiv.textContent = `Hint: (${hint.row}, ${hint.col}) ${hint.explanation}`;
+            render();
+        } else if (type === 'analyze') {
+            thinking = false;
+            messageDiv.textContent = '';
+            showLines('Your best moves', e.data.lines);
+        }
+    };
+    worker.postMessage({ type: 'start', ...aiSettings });
+    aiWorker = worker;
+}
+
+// Ask the worker for the AI's move in the current position.
+function startAiTurn() {
+    thinking = true;
+    bestSoFar = null;
+    messageDiv.textContent = 'AI is thinking...';
+    aiWorker.postMessage({ type: 'search', moves });
+}
+
+// Stop the AI search and play the best move found so far. The worker is
+// busy searching and cannot be interrupted, so it is replaced by a fresh
+// one that replays the game before its next search.
+function stopAiTurn() {
+    if (!thinking || !bestSoFar) return;
+    aiWorker.terminate();
+    startWorker();
+    playAiMove(bestSoFar);
+}
+
+// Play the move of an AI search result.
+function playAiMove(search) {
+    thinking = false;
+    stopButton.disabled = true;
+    messageDiv.textContent = '';
+    lastSearch = search;
+    showSearch(search);
+    const { row, col } = search;
+    game.make_move(row, col);
+    moves.push([row, col]);
+    const aiNow = performance.now();
+    recentMoves.push({ row, col, player: 2, time: aiNow });
+    lastMove = { row, col, player: 2, time: aiNow };
+    hint = null;
+    updateThreats();
+    if (checkGameEnd('AI wins')) return;
+    game.switch_player();
+}
+
+// End the game if the last move won it or left it drawn. Draws are found
+// as soon as neither side can make five any more.
+function checkGameEnd(winMessage) {
+    const status = game.status();
+    if (status === 0) return false;
+    endGame(status === 3 ? 'Draw!' : winMessage);
+    return true;
+}
+
 // Initialise a new game and optionally let the AI play first.
 function startGame() {
     game = new WasmGomoku();
+    puzzle = null;
+    solutionButton.disabled = true;
+    puzzleButton.textContent = 'Puzzle';
+    // A fresh seed per game so the AI does not repeat the same game.
+    const seed = Math.floor(Math.random() * 2 ** 32);
+    aiSettings = { engine: engineSelect.value, level: levelSelect.value, seed };
+    if (aiWorker) aiWorker.terminate();
+    startWorker();
     gameOver = false;
+    thinking = false;
+    moves = [];
+    lastSearch = null;
+    hint = null;
     messageDiv.textContent = '';
     infoDiv.textContent = '';
+    searchDiv.textContent = '';
+    showLines('', []);
+    analyzeButton.disabled = false;
+    drawButton.disabled = false;
+    hintButton.disabled = false;
     startButton.disabled = true; // disable startButton when game is started.
     playerFirstRadio.disabled = true;
     aiFirstRadio.disabled = true;
+    engineSelect.disabled = true;
+    levelSelect.disabled = true;
     recentMoves = [];
     lastMove = null;
     if (animRequestId) {
         cancelAnimationFrame(animRequestId);
         animRequestId = null;
     }
-    render();
+    updateThreats();
     if (aiFirstRadio.checked) {
-        const aiMove = game.ai_move();
-        game.make_move(aiMove[0], aiMove[1]);
-        const aiNow = performance.now();
-        recentMoves.push({ row: aiMove[0], col: aiMove[1], player: 2, time: aiNow });
-        lastMove = { row: aiMove[0], col: aiMove[1], player: 2, time: aiNow };
-        render();
-        const winner = game.check_winner();
-        if (winner === 2 || game.is_board_full()) {
-            endGame(winner === 2 ? 'AI wins' : 'Draw!');
-            return;
-        }
-        game.switch_player();
+        startAiTurn();
+    }
+}
+
+// Show the next built-in puzzle in place of the game. Clicks on the board
+// then play the solver's moves and the defender answers automatically.
+function startPuzzle() {
+    if (aiWorker) aiWorker.terminate();
+    aiWorker = null;
+    thinking = false;
+    gameOver = false;
+    puzzle = new WasmPuzzle(puzzleIndex);
+    puzzleIndex = (puzzleIndex + 1) % puzzle_count();
+    const player = puzzle.player() === 1 ? 'Black' : 'White';
+    messageDiv.textContent = `${puzzle.name()}: ${player} to move and ${puzzle.description()}`;
+    searchDiv.textContent = '';
+    showLines('', []);
+    startButton.disabled = false;
+    startButton.textContent = 'Start';
+    playerFirstRadio.disabled = false;
+    aiFirstRadio.disabled = false;
+    engineSelect.disabled = false;
+    levelSelect.disabled = false;
+    analyzeButton.disabled = true;
+    stopButton.disabled = true;
+    drawButton.disabled = true;
+    hintButton.disabled = true;
+    solutionButton.disabled = false;
+    puzzleButton.textContent = 'Next puzzle';
+    lastSearch = null;
+    hint = null;
+    recentMoves = [];
+    lastMove = null;
+    updateThreats();
+}
+
+// Play a move of the puzzle and the defender's reply if it was correct.
+function playPuzzleMove(row, col) {
+    const { verdict, reply } = puzzle.play(row, col);
+    if (verdict === 'invalid') return;
+    if (verdict === 'wrong') {
+        messageDiv.textContent = 'That does not win. Try again.';
+        return;
+    }
+    const now = performance.now();
+    const player = puzzle.player();
+    recentMoves.push({ row, col, player, time: now });
+    lastMove = { row, col, player, time: now };
+    if (verdict === 'solved') {
+        messageDiv.textContent = 'Solved!';
+    } else {
+        const [r, c] = reply;
+        recentMoves.push({ row: r, col: c, player: 3 - player, time: now });
+        lastMove = { row: r, col: c, player: 3 - player, time: now };
+        messageDiv.textContent = `Correct. The defender answers at (${r}, ${c}).`;
     }
+    render();
+}
+
+// Format a list of [row, col] moves.
+function formatMoves(list) {
+    return list.map(([r, c]) => `(${r}, ${c})`).join(' ');
+}
+
+// Fill the analysis panel with ranked { score, pv } lines.
+function showLines(title, lines) {
+    analysisTitle.textContent = title;
+    analysisLines.replaceChildren(...lines.map((line) => {
+        const item = document.createElement('li');
+        item.textContent = `${line.score}: ${formatMoves(line.pv)}`;
+        return item;
+    }));
 }
 
+// Show the statistics and best lines of an AI search.
+function showSearch(s) {
+    searchDiv.textContent =
+        `depth ${s.depth}, score ${s.score}, ${s.nodes} nodes in ${s.elapsed_ms} ms (${s.nps} nps), pv ${formatMoves(s.pv)}`;
+    showLines('AI candidates', s.lines);
+}
+
+// Offer the AI a draw on your turn; it accepts when it stands worse or
+// the game is dead.
+drawButton.addEventListener('click', () => {
+    if (gameOver || !game || thinking) return;
+    if (game.offer_draw()) {
+        endGame('Draw agreed');
+    } else {
+        messageDiv.textContent = 'AI declines the draw';
+    }
+});
+
+// Suggest a move for you, outlined on the board until the next move. The
+// worker searches it, so the board waits for its answer.
+hintButton.addEventListener('click', () => {
+    if (gameOver || !aiWorker || thinking) return;
+    thinking = true;
+    messageDiv.textContent = 'Looking for a hint...';
+    aiWorker.postMessage({ type: 'hint', moves });
+});
+
+// Rank the best moves for the player to move, searched by the worker.
+analyzeButton.addEventListener('click', () => {
+    if (gameOver || !aiWorker || thinking) return;
+    thinking = true;
+    messageDiv.textContent = 'Analyzing...';
+    aiWorker.postMessage({ type: 'analyze', moves, count: 5 });
+});
+
 canvas.addEventListener('click', (e) => {
-    if (gameOver || !game) return;
+    if (gameOver || !game || thinking) return;
     const rect = canvas.getBoundingClientRect();
     const x = e.clientX - rect.left;
     const y = e.clientY - rect.top;
     const col = Math.floor(x / (canvas.width / BOARD_SIZE));
     const row = Math.floor(y / (canvas.height / BOARD_SIZE));
+    if (puzzle) {
+        playPuzzleMove(row, col);
+        return;
+    }
     if (!game.make_move(row, col)) return;
     const now = performance.now();
     recentMoves.push({ row, col, player: 1, time: now });
     lastMove = { row, col, player: 1, time: now };
-    render();
-    let winner = game.check_winner();
-    if (winner === 1 || game.is_board_full()) { // combine this 'if' and following 'if' into one. because both contents of 'if' are similiar.
-        endGame(winner === 1 ? 'You win!' : 'Draw!');
-        return;
-    }
-    game.switch_player();
-    const aiMove = game.ai_move();
-    game.make_move(aiMove[0], aiMove[1]);
-    const aiNow = performance.now();
-    recentMoves.push({ row: aiMove[0], col: aiMove[1], player: 2, time: aiNow });
-    lastMove = { row: aiMove[0], col: aiMove[1], player: 2, time: aiNow };
-    render();
-    winner = game.check_winner();
-    if (winner === 2 || game.is_board_full()) { // combine this 'if' and following 'if' into one. because both contents of 'if' are similiar.
-        endGame(winner === 2 ? 'AI wins' : 'Draw!');
-        return;
-    }
+    hint = null;
+    updateThreats();
+    if (checkGameEnd('You win!')) return;
+    moves.push([row, col]);
     game.switch_player();
+    startAiTurn();
 });
 
 canvas.addEventListener('mousemove', (e) => {
@@ -287,7 +536,8 @@ canvas.addEventListener('mousemove', (e) => {
         infoDiv.textContent = '';
         return;
     }
-    const val = game.evaluation_at(row, col);
+    const evaluation = lastSearch && lastSearch.evaluations.find(([r, c]) => r === row && c === col);
+    const val = evaluation ? evaluation[2] : undefined;
     if (val === undefined || currentBoard[row][col] !== 0) {
         infoDiv.textContent = `(${row}, ${col}): N/A`;
     } else {
@@ -295,7 +545,16 @@ canvas.addEventListener('mousemove', (e) => {
     }
 });
 
+// Show a winning line from the current puzzle position.
+solutionButton.addEventListener('click', () => {
+    if (!puzzle) return;
+    messageDiv.textContent = `Solution: ${formatMoves(puzzle.solution())}`;
+});
+
 startButton.addEventListener('click', startGame);
+puzzleButton.addEventListener('click', startPuzzle);
+stopButton.addEventListener('click', stopAiTurn);
+threatsToggle.addEventListener('change', updateThreats);
 
 init().then(() => {
     BOARD_SIZE = board_size();
@@ -0,0 +1,52 @@
+// Searches the AI's moves off the main thread so the page stays responsive.
+//
+// Messages: `{ type: 'start', engine, level, seed }` sets up a new game and
+// `{ type: 'search', moves }` catches up with the moves played so far and
+// searches the AI's move, answering with a `progress` message per
+// completed depth and a final `done` message, each carrying the search
+// object of `WasmGomoku.ai_search`. `{ type: 'hint', moves }` and
+// `{ type: 'analyze', moves, count }` catch up the same way and answer
+// with a message of the same type carrying the `hint` object of
+// `WasmGomoku.hint` or the `lines` of `WasmGomoku.analyze`.
+import init, { WasmGomoku } from '../pkg/gomoku.js';
+
+const ready = init();
+let game;
+let played = 0;
+
+// Play the moves of `moves` the game has not seen yet.
+function catchUp(moves) {
+    for (; played < moves.length; played++) {
+        const [row, col] = moves[played];
+        game.make_move(row, col);
+        game.switch_player();
+    }
+}
+
+self.onmessage = async (e) => {
+    await ready;
+    const msg = e.data;
+    if (msg.type === 'start') {
+        game = new WasmGomoku();
+        played = 0;
+        game.set_engine(msg.engine);
+        if (msg.level) {
+            game.set_difficulty(msg.level, msg.seed);
+        } else {
+            game.set_move_selection('tie', 0);
+            game.set_seed(msg.seed);
+        }
+    } else if (msg.type === 'search') {
+        catchUp(msg.moves);
+        const search = game.ai_search((progress) => {
+            self.postMessage({ type: 'progress', search: progress });
+        });
+        self.postMessage({ type: 'done', search });
+    } else if (msg.type === 'hint') {
+        catchUp(msg.moves);
+        self.postMessage({ type: 'hint', hint: game.hint() });
+    } else if (msg.type === 'analyze') {
+        catchUp(msg.moves);
+        self.postMessage({ type: 'analyze', lines: game.analyze(msg.count) });
+    }
+};